@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use sqlx::MySqlConnection;
+
+use crate::{
+    auth::{password::hash_password, token::generate_token},
+    avatar::avatar_dir,
+    db::Db,
+    storage::Store,
+};
+
+// time a user has to change their mind before the deletion runs
+pub const ACCOUNT_DELETION_COOL_OFF_DAYS: i64 = 14;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionMode {
+    // remove the account together with its posts and comments
+    Delete,
+    // scrub personal data, posts and comments stay under a "deleted user" tombstone
+    Anonymise,
+}
+
+impl DeletionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeletionMode::Delete => "delete",
+            DeletionMode::Anonymise => "anonymise",
+        }
+    }
+
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "delete" => Some(DeletionMode::Delete),
+            "anonymise" => Some(DeletionMode::Anonymise),
+            _ => None,
+        }
+    }
+}
+
+// comments no longer cascade with their author, so they are removed here on purpose
+pub async fn hard_delete_user(
+    conn: &mut MySqlConnection,
+    user_id: i64,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query!("DELETE FROM comments WHERE author_id = ?", user_id)
+        .execute(&mut *conn)
+        .await?;
+    // posts have no foreign key to users, comments on them go with the post
+    sqlx::query!("DELETE FROM posts WHERE author_id = ?", user_id)
+        .execute(&mut *conn)
+        .await?;
+    let result = sqlx::query!("DELETE FROM users WHERE id = ?", user_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// keeps the row so posts and comments still have an author, but nothing personal
+pub async fn anonymise_user(conn: &mut MySqlConnection, user_id: i64) -> Result<bool, sqlx::Error> {
+    // nobody knows this password, the account can never sign in again
+    let password_hash = hash_password(&generate_token());
+    let result = sqlx::query!(
+        "UPDATE users SET username = 'deleted user', handle = CONCAT('deleted-', id),
+         email = CONCAT('deleted-', id, '@users.invalid'), password = ?, display_name = NULL,
+         bio = NULL, website = NULL, avatar_version = NULL, email_verified_at = NULL, totp_secret = NULL,
+         totp_enabled_at = NULL, totp_last_step = NULL, role = 'reader', deleted_at = NOW()
+         WHERE id = ?",
+        password_hash,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!("DELETE FROM sessions WHERE user_id = ?", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM api_keys WHERE user_id = ?", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM password_resets WHERE user_id = ?", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = ?", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM user_identities WHERE user_id = ?", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM handle_redirects WHERE user_id = ?", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM account_deletions WHERE user_id = ?", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "DELETE FROM two_factor_challenges WHERE user_id = ?",
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    // the tombstone neither follows nor is followed, and its block and mute lists go too
+    sqlx::query!(
+        "DELETE FROM follows WHERE follower_id = ? OR followee_id = ?",
+        user_id,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM blocks WHERE blocker_id = ? OR blocked_id = ?",
+        user_id,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM mutes WHERE muter_id = ? OR muted_id = ?",
+        user_id,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// carry out deletions whose cool-off has passed, one row per transaction
+pub async fn run_due_deletions(db_pool: &Db, storage: &Store) -> Result<u64, sqlx::Error> {
+    let mut processed = 0;
+    loop {
+        let mut tx = db_pool.begin().await?;
+        let due = sqlx::query!(
+            "SELECT user_id, mode FROM account_deletions WHERE scheduled_for <= NOW()
+             ORDER BY scheduled_for LIMIT 1 FOR UPDATE SKIP LOCKED"
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(due) = due else {
+            return Ok(processed);
+        };
+
+        let user_id = due.user_id as i64;
+        match DeletionMode::parse(&due.mode) {
+            Some(DeletionMode::Anonymise) => {
+                anonymise_user(&mut *tx, user_id).await?;
+            }
+            // unknown modes fall back to the stricter option
+            _ => {
+                hard_delete_user(&mut *tx, user_id).await?;
+            }
+        }
+        tx.commit().await?;
+        // both modes drop the avatar, a leftover file is only wasted space
+        let _ = storage.delete(&avatar_dir(user_id)).await;
+        processed += 1;
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{
+    auth::token::{generate_token, hash_token},
+    db::Db,
+};
+
+// every key starts with this prefix so guards can tell it apart from a jwt
+pub const API_KEY_PREFIX: &str = "blog_";
+
+pub struct ApiKeyPrincipal {
+    pub user_id: i64,
+    pub scopes: Vec<String>,
+}
+
+pub fn generate_api_key() -> String {
+    format!("{}{}", API_KEY_PREFIX, generate_token())
+}
+
+// look up an active key and record that it was used
+pub async fn authenticate_api_key(
+    db_pool: &Db,
+    api_key: &str,
+) -> Result<Option<ApiKeyPrincipal>, sqlx::Error> {
+    let record = sqlx::query!(
+        "SELECT id, user_id, scopes FROM api_keys
+         WHERE key_hash = ? AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())",
+        hash_token(api_key)
+    )
+    .fetch_optional(db_pool)
+    .await?;
+
+    let Some(record) = record else {
+        return Ok(None);
+    };
+
+    sqlx::query!(
+        "UPDATE api_keys SET last_used_at = NOW() WHERE id = ?",
+        record.id
+    )
+    .execute(db_pool)
+    .await?;
+
+    Ok(Some(ApiKeyPrincipal {
+        user_id: record.user_id as i64,
+        scopes: record.scopes.split(' ').map(str::to_string).collect(),
+    }))
+}
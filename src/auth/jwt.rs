@@ -1,7 +1,8 @@
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
+use jsonwebtoken::TokenData;
 use serde::{Deserialize, Serialize};
-use std::env;
+
+use super::keys::Keyring;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
@@ -9,14 +10,19 @@ pub struct Claims {
     pub exp: usize,   // Expiration time (in seconds since the epoch)
     pub iat: usize,   // Issued at time (in seconds since the epoch)
     pub role: String, // User role (e.g., "admin", "user")
+    pub sid: i64,     // Session the token was issued for
 }
 
+pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+
 pub fn generate_jwt(
+    keyring: &Keyring,
     user_id: &String,
     role: &String,
+    session_id: i64,
 ) -> Result<String, jsonwebtoken::errors::Error> {
     let expiration = Utc::now()
-        .checked_add_signed(Duration::seconds(3600 * 24 * 7)) // Token valid for 7 week
+        .checked_add_signed(Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)) // short lived, renewed through /auth/refresh
         .expect("valid timestamp")
         .timestamp() as usize;
 
@@ -25,22 +31,88 @@ pub fn generate_jwt(
         exp: expiration,
         iat: Utc::now().timestamp() as usize,
         role: role.clone(),
+        sid: session_id,
+    };
+    keyring.sign(&claims)
+}
+
+pub fn verify_jwt(
+    keyring: &Keyring,
+    token: &str,
+) -> Result<TokenData<Claims>, jsonwebtoken::errors::Error> {
+    keyring.verify::<Claims>(token)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailVerificationClaims {
+    pub sub: String,   // User the link was sent to
+    pub email: String, // Address being verified, a changed email invalidates old links
+    pub exp: usize,
+}
+
+pub const EMAIL_VERIFICATION_TTL_HOURS: i64 = 24;
+
+pub fn generate_email_verification_token(
+    keyring: &Keyring,
+    user_id: &String,
+    email: &String,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let expiration = Utc::now()
+        .checked_add_signed(Duration::hours(EMAIL_VERIFICATION_TTL_HOURS))
+        .expect("valid timestamp")
+        .timestamp() as usize;
+
+    let claims = EmailVerificationClaims {
+        sub: user_id.clone(),
+        email: email.clone(),
+        exp: expiration,
     };
-    let secret = env::var("SECRET").expect("error please provide SECRET in .env file");
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_ref()),
-    )?;
-    Ok(token)
-}
-
-pub fn verify_jwt(token: &str) -> Result<TokenData<Claims>, jsonwebtoken::errors::Error> {
-    let secret = env::var("SECRET").expect("error please provide SECRET in .env file");
-    let decoded = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &Validation::default(),
-    )?;
+    keyring.sign(&claims)
+}
+
+pub fn verify_email_verification_token(
+    keyring: &Keyring,
+    token: &str,
+) -> Result<TokenData<EmailVerificationClaims>, jsonwebtoken::errors::Error> {
+    keyring.verify::<EmailVerificationClaims>(token)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorChallengeClaims {
+    pub sub: String, // User who passed the password check
+    pub exp: usize,
+    pub purpose: String, // Always "2fa", so no other token can stand in for a challenge
+    pub jti: String,     // Row in two_factor_challenges, which makes the challenge single use
+}
+
+pub const TWO_FACTOR_CHALLENGE_TTL_MINUTES: i64 = 5;
+
+pub fn generate_two_factor_challenge(
+    keyring: &Keyring,
+    user_id: &String,
+    jti: &String,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let expiration = Utc::now()
+        .checked_add_signed(Duration::minutes(TWO_FACTOR_CHALLENGE_TTL_MINUTES))
+        .expect("valid timestamp")
+        .timestamp() as usize;
+
+    let claims = TwoFactorChallengeClaims {
+        sub: user_id.clone(),
+        exp: expiration,
+        purpose: "2fa".to_string(),
+        jti: jti.clone(),
+    };
+    keyring.sign(&claims)
+}
+
+pub fn verify_two_factor_challenge(
+    keyring: &Keyring,
+    token: &str,
+) -> Result<TokenData<TwoFactorChallengeClaims>, jsonwebtoken::errors::Error> {
+    let decoded = keyring.verify::<TwoFactorChallengeClaims>(token)?;
+    if decoded.claims.purpose != "2fa" {
+        return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+    }
     Ok(decoded)
 }
@@ -0,0 +1,188 @@
+use std::{env, fs, path::PathBuf};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    errors::{Error, ErrorKind},
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation,
+};
+use rsa::{pkcs8::DecodePublicKey, traits::PublicKeyParts, RsaPublicKey};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+// one entry of the JWT_KEYS_FILE manifest, paths are relative to the working directory
+#[derive(Deserialize)]
+struct KeyManifestEntry {
+    kid: String,
+    alg: Algorithm,
+    // keys that are only kept around to verify old tokens can leave it out
+    private_key: Option<PathBuf>,
+    public_key: PathBuf,
+    // the newest key past `active_from` signs, every key verifies until `expires_at`
+    active_from: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+struct SigningKey {
+    kid: String,
+    alg: Algorithm,
+    encoding_key: Option<EncodingKey>,
+    decoding_key: DecodingKey,
+    jwk: Jwk,
+    active_from: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+pub struct Keyring {
+    keys: Vec<SigningKey>,
+}
+
+fn read_key(path: &PathBuf) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|e| panic!("faild to read key {}: {}", path.display(), e))
+}
+
+fn load_key(entry: KeyManifestEntry) -> SigningKey {
+    let public_pem = read_key(&entry.public_key);
+    let (decoding_key, params, key_algorithm) = match entry.alg {
+        Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => {
+            let public_key =
+                RsaPublicKey::from_public_key_pem(&String::from_utf8_lossy(&public_pem))
+                    .expect("invalid RSA public key");
+            let params = AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be()),
+                e: URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be()),
+            });
+            let key_algorithm = match entry.alg {
+                Algorithm::RS256 => KeyAlgorithm::RS256,
+                Algorithm::RS384 => KeyAlgorithm::RS384,
+                _ => KeyAlgorithm::RS512,
+            };
+            (
+                DecodingKey::from_rsa_pem(&public_pem).expect("invalid RSA public key"),
+                params,
+                key_algorithm,
+            )
+        }
+        Algorithm::EdDSA => {
+            // an Ed25519 SubjectPublicKeyInfo is a 12 byte header followed by the raw key
+            let der = pem::parse(&public_pem).expect("invalid Ed25519 public key");
+            let raw_key = der
+                .contents()
+                .get(12..)
+                .filter(|key| key.len() == 32)
+                .expect("invalid Ed25519 public key");
+            let params = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(raw_key),
+            });
+            (
+                DecodingKey::from_ed_pem(&public_pem).expect("invalid Ed25519 public key"),
+                params,
+                KeyAlgorithm::EdDSA,
+            )
+        }
+        other => panic!("unsupported jwt algorithm {:?}, use RS256 or EdDSA", other),
+    };
+
+    let encoding_key = entry.private_key.as_ref().map(|path| {
+        let private_pem = read_key(path);
+        match entry.alg {
+            Algorithm::EdDSA => EncodingKey::from_ed_pem(&private_pem),
+            _ => EncodingKey::from_rsa_pem(&private_pem),
+        }
+        .expect("invalid private key")
+    });
+
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(entry.kid.clone()),
+            ..Default::default()
+        },
+        algorithm: params,
+    };
+
+    SigningKey {
+        kid: entry.kid,
+        alg: entry.alg,
+        encoding_key,
+        decoding_key,
+        jwk,
+        active_from: entry.active_from,
+        expires_at: entry.expires_at,
+    }
+}
+
+impl Keyring {
+    pub fn from_env() -> Self {
+        let path =
+            env::var("JWT_KEYS_FILE").expect("error please provide JWT_KEYS_FILE in .env file");
+        let manifest =
+            fs::read_to_string(&path).unwrap_or_else(|e| panic!("faild to read {}: {}", path, e));
+        let entries: Vec<KeyManifestEntry> =
+            rocket::serde::json::from_str(&manifest).expect("invalid JWT_KEYS_FILE manifest");
+        let keys: Vec<SigningKey> = entries.into_iter().map(load_key).collect();
+        assert!(
+            keys.iter().any(|key| key.encoding_key.is_some()),
+            "JWT_KEYS_FILE needs at least one key with a private key"
+        );
+        Keyring { keys }
+    }
+
+    // newest key that has become active and has not expired yet
+    fn signing_key(&self) -> Option<&SigningKey> {
+        let now = Utc::now();
+        self.keys
+            .iter()
+            .filter(|key| key.encoding_key.is_some())
+            .filter(|key| key.active_from <= now && now < key.expires_at)
+            .max_by_key(|key| key.active_from)
+    }
+
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, Error> {
+        let key = self
+            .signing_key()
+            .ok_or_else(|| Error::from(ErrorKind::InvalidKeyFormat))?;
+        let mut header = Header::new(key.alg);
+        header.kid = Some(key.kid.clone());
+        encode(
+            &header,
+            claims,
+            key.encoding_key
+                .as_ref()
+                .expect("signing key has a private key"),
+        )
+    }
+
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<TokenData<T>, Error> {
+        let header = decode_header(token)?;
+        let now = Utc::now();
+        // an unknown or expired kid means we no longer trust whoever signed it
+        let key = header
+            .kid
+            .and_then(|kid| self.keys.iter().find(|key| key.kid == kid))
+            .filter(|key| now < key.expires_at)
+            .ok_or_else(|| Error::from(ErrorKind::InvalidSignature))?;
+        decode::<T>(token, &key.decoding_key, &Validation::new(key.alg))
+    }
+
+    // public keys of every key that has not expired, including ones that are not active yet
+    pub fn jwks(&self) -> JwkSet {
+        let now = Utc::now();
+        JwkSet {
+            keys: self
+                .keys
+                .iter()
+                .filter(|key| now < key.expires_at)
+                .map(|key| key.jwk.clone())
+                .collect(),
+        }
+    }
+}
@@ -1,2 +1,10 @@
+pub mod api_key;
 pub mod jwt;
+pub mod keys;
 pub mod password;
+pub mod permissions;
+pub mod session;
+pub mod suspension;
+pub mod throttle;
+pub mod token;
+pub mod totp;
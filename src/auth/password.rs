@@ -2,6 +2,8 @@ use argon2::{
     password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
     Argon2, PasswordHash, PasswordVerifier,
 };
+use std::sync::OnceLock;
+
 pub fn verify_password(
     password: &String,
     password_hash: &String,
@@ -19,3 +21,11 @@ pub fn hash_password(password: &String) -> String {
     let password_hash = argon2.hash_password(password_bytes, &salt).unwrap();
     password_hash.to_string()
 }
+
+// run a verification against a throwaway hash so unknown emails take as long as wrong passwords
+pub fn verify_dummy_password(password: &String) {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    let password_hash =
+        DUMMY_HASH.get_or_init(|| hash_password(&"not a real password".to_string()));
+    let _ = verify_password(password, password_hash);
+}
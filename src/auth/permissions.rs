@@ -0,0 +1,74 @@
+use rocket::{http::Status, response::status, serde::json::Json};
+
+use crate::{db::Db, models::error::ResponseError};
+
+// permission names as stored in the permissions table
+pub const POST_CREATE: &str = "post.create";
+pub const POST_UPDATE_OWN: &str = "post.update.own";
+pub const POST_UPDATE_ANY: &str = "post.update.any";
+pub const POST_DELETE_OWN: &str = "post.delete.own";
+pub const POST_DELETE_ANY: &str = "post.delete.any";
+pub const COMMENT_CREATE: &str = "comment.create";
+pub const COMMENT_UPDATE_OWN: &str = "comment.update.own";
+pub const COMMENT_DELETE_OWN: &str = "comment.delete.own";
+pub const COMMENT_MODERATE: &str = "comment.moderate";
+pub const USER_MANAGE: &str = "user.manage";
+pub const TAXONOMY_MANAGE: &str = "taxonomy.manage";
+
+// looks up the user's current role, so a changed role applies without a new token
+pub async fn has_permission(
+    db_pool: &Db,
+    user_id: i64,
+    permission: &str,
+) -> Result<bool, sqlx::Error> {
+    let granted = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM users u JOIN role_permissions rp ON rp.role = u.role
+         WHERE u.id = ? AND rp.permission = ?)",
+        user_id,
+        permission
+    )
+    .fetch_one(db_pool)
+    .await?;
+    Ok(granted != 0)
+}
+
+pub async fn require_permission(
+    db_pool: &Db,
+    user_id: i64,
+    permission: &str,
+) -> Result<(), status::Custom<Json<ResponseError>>> {
+    let granted = has_permission(db_pool, user_id, permission)
+        .await
+        .map_err(|_| {
+            status::Custom(
+                Status::InternalServerError,
+                Json(ResponseError {
+                    error: "Database Error".to_string(),
+                }),
+            )
+        })?;
+    if !granted {
+        return Err(status::Custom(
+            Status::Forbidden,
+            Json(ResponseError {
+                error: format!("Missing permission: {}", permission),
+            }),
+        ));
+    }
+    Ok(())
+}
+
+// for actions that differ between the user's own content and everyone else's
+pub async fn require_ownership_permission(
+    db_pool: &Db,
+    user_id: i64,
+    owner_id: i64,
+    own_permission: &str,
+    any_permission: &str,
+) -> Result<(), status::Custom<Json<ResponseError>>> {
+    if user_id == owner_id {
+        require_permission(db_pool, user_id, own_permission).await
+    } else {
+        require_permission(db_pool, user_id, any_permission).await
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    auth::token::{generate_token, hash_token},
+    db::Db,
+};
+
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+pub struct ActiveSession {
+    pub id: i64,
+    pub user_id: i64,
+    pub role: String,
+}
+
+// open a new session and return its id together with the plain refresh token
+pub async fn create_session(db_pool: &Db, user_id: i64) -> Result<(i64, String), sqlx::Error> {
+    let refresh_token = generate_token();
+    let result = sqlx::query!(
+        "INSERT INTO sessions (user_id, refresh_token_hash, expires_at) VALUES (?, ?, NOW() + INTERVAL ? DAY)",
+        user_id,
+        hash_token(&refresh_token),
+        REFRESH_TOKEN_TTL_DAYS
+    )
+    .execute(db_pool)
+    .await?;
+    Ok((result.last_insert_id() as i64, refresh_token))
+}
+
+// swap the refresh token of an active session for a new one,
+// returns None when the token is unknown, expired or revoked
+pub async fn rotate_session(
+    db_pool: &Db,
+    refresh_token: &str,
+) -> Result<Option<(ActiveSession, String)>, sqlx::Error> {
+    let mut tx = db_pool.begin().await?;
+    let record = sqlx::query!(
+        "SELECT s.id, s.user_id, u.role FROM sessions s JOIN users u ON u.id = s.user_id
+         WHERE s.refresh_token_hash = ? AND s.revoked_at IS NULL AND s.expires_at > NOW()
+         FOR UPDATE",
+        hash_token(refresh_token)
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(record) = record else {
+        return Ok(None);
+    };
+
+    let new_refresh_token = generate_token();
+    sqlx::query!(
+        "UPDATE sessions SET refresh_token_hash = ?, expires_at = NOW() + INTERVAL ? DAY WHERE id = ?",
+        hash_token(&new_refresh_token),
+        REFRESH_TOKEN_TTL_DAYS,
+        record.id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let session = ActiveSession {
+        id: record.id as i64,
+        user_id: record.user_id as i64,
+        role: record.role,
+    };
+    Ok(Some((session, new_refresh_token)))
+}
+
+pub async fn revoke_session(
+    db_pool: &Db,
+    session_id: i64,
+    user_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE sessions SET revoked_at = NOW() WHERE id = ? AND user_id = ? AND revoked_at IS NULL",
+        session_id,
+        user_id
+    )
+    .execute(db_pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn is_session_active(db_pool: &Db, session_id: i64) -> Result<bool, sqlx::Error> {
+    let active = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM sessions WHERE id = ? AND revoked_at IS NULL AND expires_at > NOW())",
+        session_id
+    )
+    .fetch_one(db_pool)
+    .await?;
+    Ok(active != 0)
+}
+
+// keeps the session that made the change signed in
+pub async fn revoke_other_sessions(
+    db_pool: &Db,
+    user_id: i64,
+    session_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE sessions SET revoked_at = NOW() WHERE user_id = ? AND id <> ? AND revoked_at IS NULL",
+        user_id,
+        session_id
+    )
+    .execute(db_pool)
+    .await?;
+    Ok(())
+}
+
+// used when the credentials change, every device has to sign in again
+pub async fn revoke_all_sessions<'e, E>(executor: E, user_id: i64) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = sqlx::MySql>,
+{
+    sqlx::query!(
+        "UPDATE sessions SET revoked_at = NOW() WHERE user_id = ? AND revoked_at IS NULL",
+        user_id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use crate::db::Db;
+
+// a suspension without an end date is a ban
+pub async fn is_suspended(db_pool: &Db, user_id: i64) -> Result<bool, sqlx::Error> {
+    let suspended = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE id = ? AND suspended_at IS NOT NULL
+         AND (suspended_until IS NULL OR suspended_until > NOW()))",
+        user_id
+    )
+    .fetch_one(db_pool)
+    .await?;
+    Ok(suspended != 0)
+}
@@ -0,0 +1,77 @@
+use crate::db::Db;
+
+// failures inside this window count towards a lockout, older ones are forgotten
+const ATTEMPT_WINDOW_MINUTES: i64 = 15;
+const MAX_ATTEMPTS_PER_EMAIL: i64 = 5;
+const MAX_ATTEMPTS_PER_IP: i64 = 20;
+// the lockout doubles with every failure past the limit, up to an hour
+const BASE_LOCKOUT_SECONDS: i64 = 30;
+const MAX_LOCKOUT_SECONDS: i64 = 3600;
+
+// seconds until the email and the client ip may try again, None when neither is locked
+pub async fn lockout_remaining(
+    db_pool: &Db,
+    email: &str,
+    ip: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    let remaining = sqlx::query_scalar!(
+        "SELECT MAX(TIMESTAMPDIFF(SECOND, NOW(), locked_until)) FROM login_throttles
+         WHERE ((scope = 'email' AND identifier = ?) OR (scope = 'ip' AND identifier = ?))
+         AND locked_until > NOW()",
+        email,
+        ip
+    )
+    .fetch_one(db_pool)
+    .await?;
+    Ok(remaining.map(|seconds| seconds.max(1)))
+}
+
+async fn record_failure(
+    db_pool: &Db,
+    scope: &str,
+    identifier: &str,
+    max_attempts: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO login_throttles (scope, identifier, failed_count, last_failed_at) VALUES (?, ?, 1, NOW())
+         ON DUPLICATE KEY UPDATE
+            failed_count = IF(last_failed_at < NOW() - INTERVAL ? MINUTE, 1, failed_count + 1),
+            last_failed_at = NOW()",
+        scope,
+        identifier,
+        ATTEMPT_WINDOW_MINUTES
+    )
+    .execute(db_pool)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE login_throttles
+         SET locked_until = NOW() + INTERVAL LEAST(? * POW(2, failed_count - ?), ?) SECOND
+         WHERE scope = ? AND identifier = ? AND failed_count >= ?",
+        BASE_LOCKOUT_SECONDS,
+        max_attempts,
+        MAX_LOCKOUT_SECONDS,
+        scope,
+        identifier,
+        max_attempts
+    )
+    .execute(db_pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn record_failed_sign_in(db_pool: &Db, email: &str, ip: &str) -> Result<(), sqlx::Error> {
+    record_failure(db_pool, "email", email, MAX_ATTEMPTS_PER_EMAIL).await?;
+    record_failure(db_pool, "ip", ip, MAX_ATTEMPTS_PER_IP).await
+}
+
+// a successful sign in resets the account, the ip counter only decays with time
+pub async fn clear_failed_sign_ins(db_pool: &Db, email: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM login_throttles WHERE scope = 'email' AND identifier = ?",
+        email
+    )
+    .execute(db_pool)
+    .await?;
+    Ok(())
+}
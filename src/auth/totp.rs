@@ -0,0 +1,193 @@
+use std::{
+    env,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use sqlx::MySqlConnection;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::{
+    auth::{jwt::TWO_FACTOR_CHALLENGE_TTL_MINUTES, token::hash_token},
+    db::Db,
+};
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+// codes that may be tried against one sign in challenge
+pub const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+// 160 bit secret as recommended by RFC 4226, stored base32 encoded
+pub fn generate_secret() -> String {
+    let mut bytes = vec![0u8; 20];
+    OsRng.fill_bytes(&mut bytes);
+    Secret::Raw(bytes).to_encoded().to_string()
+}
+
+pub fn build_totp(secret: &str, account_name: &str) -> Option<TOTP> {
+    let issuer = env::var("TOTP_ISSUER").unwrap_or_else(|_| "blog-api".to_string());
+    let secret = Secret::Encoded(secret.to_string()).to_bytes().ok()?;
+    // SHA1, 6 digits and 30 second steps is what every authenticator app supports,
+    // a skew of 1 also accepts the previous and the next code
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret,
+        Some(issuer),
+        account_name.to_string(),
+    )
+    .ok()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// the time step `code` belongs to, within the allowed skew of the current one
+fn matching_step(secret: &str, code: &str) -> Option<u64> {
+    let totp = build_totp(secret, "")?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let current = now / totp.step;
+    let skew = totp.skew as u64;
+    let code = code.trim();
+    (current.saturating_sub(skew)..=current + skew)
+        .find(|step| constant_time_eq(totp.generate(step * totp.step).as_bytes(), code.as_bytes()))
+}
+
+// accepts a current TOTP code once, a code from the same or an earlier step is a replay
+pub async fn accept_code(
+    conn: &mut MySqlConnection,
+    user_id: i64,
+    secret: &str,
+    code: &str,
+) -> Result<bool, sqlx::Error> {
+    let Some(step) = matching_step(secret, code) else {
+        return Ok(false);
+    };
+    let step = step as i64;
+    let result = sqlx::query!(
+        "UPDATE users SET totp_last_step = ?
+         WHERE id = ? AND (totp_last_step IS NULL OR totp_last_step < ?)",
+        step,
+        user_id,
+        step
+    )
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+// codes look like 1a2b-3c4d-5e6f-7a8b
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 8];
+            OsRng.fill_bytes(&mut bytes);
+            bytes
+                .chunks(2)
+                .map(|chunk| format!("{:02x}{:02x}", chunk[0], chunk[1]))
+                .collect::<Vec<String>>()
+                .join("-")
+        })
+        .collect()
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.trim().to_lowercase().replace(' ', "")
+}
+
+// replace any previous recovery codes of the user with a fresh set
+pub async fn store_recovery_codes(
+    db_pool: &Db,
+    user_id: i64,
+    codes: &[String],
+) -> Result<(), sqlx::Error> {
+    let mut tx = db_pool.begin().await?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = ?", user_id)
+        .execute(&mut *tx)
+        .await?;
+    for code in codes {
+        sqlx::query!(
+            "INSERT INTO recovery_codes (user_id, code_hash) VALUES (?, ?)",
+            user_id,
+            hash_token(&normalize_recovery_code(code))
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+// accepts either a current TOTP code or an unused recovery code, which is then burned
+pub async fn verify_second_factor(
+    conn: &mut MySqlConnection,
+    user_id: i64,
+    secret: &str,
+    code: &str,
+) -> Result<bool, sqlx::Error> {
+    if accept_code(&mut *conn, user_id, secret, code).await? {
+        return Ok(true);
+    }
+    let result = sqlx::query!(
+        "UPDATE recovery_codes SET used_at = NOW() WHERE user_id = ? AND code_hash = ? AND used_at IS NULL",
+        user_id,
+        hash_token(&normalize_recovery_code(code))
+    )
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+// remembers a freshly issued sign in challenge, old ones of the user are dropped
+pub async fn store_challenge(db_pool: &Db, jti: &str, user_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM two_factor_challenges WHERE user_id = ? AND expires_at < NOW()",
+        user_id
+    )
+    .execute(db_pool)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO two_factor_challenges (jti, user_id, expires_at)
+         VALUES (?, ?, NOW() + INTERVAL ? MINUTE)",
+        jti,
+        user_id,
+        TWO_FACTOR_CHALLENGE_TTL_MINUTES
+    )
+    .execute(db_pool)
+    .await?;
+    Ok(())
+}
+
+// counts an attempt against the challenge, false once it was used, expired or ran out of
+// attempts. claiming before the code is checked keeps parallel guesses under the limit
+pub async fn claim_challenge_attempt(
+    db_pool: &Db,
+    jti: &str,
+    user_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "UPDATE two_factor_challenges SET attempts = attempts + 1
+         WHERE jti = ? AND user_id = ? AND consumed_at IS NULL AND expires_at > NOW()
+         AND attempts < ?",
+        jti,
+        user_id,
+        MAX_CHALLENGE_ATTEMPTS
+    )
+    .execute(db_pool)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+// true for the one request that gets to use the challenge, the row stays locked until the
+// surrounding transaction ends
+pub async fn consume_challenge(conn: &mut MySqlConnection, jti: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "UPDATE two_factor_challenges SET consumed_at = NOW()
+         WHERE jti = ? AND consumed_at IS NULL",
+        jti
+    )
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
@@ -0,0 +1,82 @@
+use std::io::Cursor;
+
+use image::{imageops::FilterType, DynamicImage, ImageError, ImageFormat, ImageReader, Limits};
+
+use crate::{config::app_url, models::user::AvatarUrls};
+
+pub const MAX_AVATAR_BYTES: u64 = 5 * 1024 * 1024;
+// refuse to decode anything bigger, guards against decompression bombs
+const MAX_AVATAR_DIMENSION: u32 = 4096;
+// square thumbnails rendered for every upload: small, medium and large
+pub const AVATAR_SIZES: [u32; 3] = [64, 128, 256];
+
+pub enum AvatarError {
+    UnsupportedFormat,
+    TooLarge,
+    Invalid,
+}
+
+impl AvatarError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            AvatarError::UnsupportedFormat => "Avatar must be a PNG, JPEG, GIF or WebP image",
+            AvatarError::TooLarge => "Avatar dimensions are too large",
+            AvatarError::Invalid => "Avatar could not be decoded",
+        }
+    }
+}
+
+// every upload gets a fresh version so the urls can be cached forever
+pub fn avatar_dir(user_id: i64) -> String {
+    format!("avatars/{}", user_id)
+}
+
+pub fn avatar_key(user_id: i64, version: &str, size: u32) -> String {
+    format!("{}/{}/{}.webp", avatar_dir(user_id), version, size)
+}
+
+pub fn avatar_urls(user_id: i64, version: Option<&str>) -> Option<AvatarUrls> {
+    let version = version?;
+    let url = |size: u32| format!("{}/{}", app_url(), avatar_key(user_id, version, size));
+    Some(AvatarUrls {
+        small: url(AVATAR_SIZES[0]),
+        medium: url(AVATAR_SIZES[1]),
+        large: url(AVATAR_SIZES[2]),
+    })
+}
+
+// decode the upload and render each thumbnail size as WebP,
+// re-encoding from pixels drops EXIF and any other embedded metadata
+pub fn render_avatar(bytes: &[u8]) -> Result<Vec<(u32, Vec<u8>)>, AvatarError> {
+    // trust the bytes, not the content type the client sent
+    let format = image::guess_format(bytes).map_err(|_| AvatarError::UnsupportedFormat)?;
+    if !matches!(
+        format,
+        ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP
+    ) {
+        return Err(AvatarError::UnsupportedFormat);
+    }
+
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_AVATAR_DIMENSION);
+    limits.max_image_height = Some(MAX_AVATAR_DIMENSION);
+    reader.limits(limits);
+    let image = reader.decode().map_err(|e| match e {
+        ImageError::Limits(_) => AvatarError::TooLarge,
+        _ => AvatarError::Invalid,
+    })?;
+
+    AVATAR_SIZES
+        .iter()
+        .map(|&size| {
+            let thumbnail = image.resize_to_fill(size, size, FilterType::Lanczos3);
+            let thumbnail = DynamicImage::ImageRgba8(thumbnail.to_rgba8());
+            let mut encoded = Vec::new();
+            thumbnail
+                .write_to(&mut Cursor::new(&mut encoded), ImageFormat::WebP)
+                .map_err(|_| AvatarError::Invalid)?;
+            Ok((size, encoded))
+        })
+        .collect()
+}
@@ -0,0 +1,58 @@
+use std::env;
+
+// where JwtAuth may look for the access token
+#[derive(Clone, Copy, PartialEq)]
+pub enum TokenSource {
+    Header,
+    Cookie,
+}
+
+pub struct AuthConfig {
+    // sources are tried in order, the first one present in the request wins
+    pub token_sources: Vec<TokenSource>,
+    // block users without a verified email from creating posts and comments
+    pub require_verified_email: bool,
+}
+
+impl AuthConfig {
+    pub fn from_env() -> Self {
+        let token_sources = env::var("AUTH_TOKEN_SOURCES")
+            .unwrap_or_else(|_| "header,cookie".to_string())
+            .split(',')
+            .map(|source| match source.trim() {
+                "header" => TokenSource::Header,
+                "cookie" => TokenSource::Cookie,
+                other => panic!("unknown token source `{}` in AUTH_TOKEN_SOURCES", other),
+            })
+            .collect();
+        let require_verified_email = env::var("REQUIRE_EMAIL_VERIFICATION")
+            .map(|value| value == "true" || value == "1")
+            .unwrap_or(false);
+        AuthConfig {
+            token_sources,
+            require_verified_email,
+        }
+    }
+}
+
+// header a trusted reverse proxy puts the client address in, e.g. X-Real-IP. Unset by
+// default so the socket address is used, a client could send the header itself otherwise
+pub fn trusted_ip_header() -> Option<String> {
+    env::var("TRUSTED_IP_HEADER")
+        .ok()
+        .map(|header| header.trim().to_string())
+        .filter(|header| !header.is_empty())
+}
+
+// days deleted posts and comments stay in the trash before they are purged
+pub fn trash_retention_days() -> i64 {
+    env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(30)
+}
+
+// public base url of the api, used to build links sent by mail
+pub fn app_url() -> String {
+    env::var("APP_URL").unwrap_or_else(|_| "http://localhost:8000".to_string())
+}
@@ -1,17 +1,120 @@
 -- @block
+CREATE TABLE IF NOT EXISTS categories (
+    id INT PRIMARY KEY AUTO_INCREMENT,
+    name VARCHAR(80) NOT NULL,
+    slug VARCHAR(90) NOT NULL UNIQUE,
+    parent_id INT NULL,
+    FOREIGN KEY(parent_id) REFERENCES categories(id) ON DELETE RESTRICT
+);
 CREATE TABLE IF NOT EXISTS posts (
     id INT PRIMARY KEY AUTO_INCREMENT,
     author_id INT NOT NULL,
     title TEXT NOT NULL,
+    slug VARCHAR(90) NOT NULL UNIQUE,
     body TEXT NOT NULL,
-    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+    -- rendered from body on write, NULL until then
+    body_html MEDIUMTEXT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'draft',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    published_at TIMESTAMP NULL,
+    publish_at TIMESTAMP NULL,
+    category_id INT NULL,
+    -- set while the post is in the trash
+    deleted_at TIMESTAMP NULL,
+    INDEX(status, published_at),
+    INDEX(status, publish_at),
+    INDEX(author_id, status),
+    INDEX(deleted_at),
+    FULLTEXT(title, body),
+    FULLTEXT(title),
+    FOREIGN KEY(category_id) REFERENCES categories(id) ON DELETE SET NULL
+);
+CREATE TABLE IF NOT EXISTS tags (
+    id INT PRIMARY KEY AUTO_INCREMENT,
+    name VARCHAR(50) NOT NULL UNIQUE
+);
+CREATE TABLE IF NOT EXISTS post_tags (
+    post_id INT NOT NULL,
+    tag_id INT NOT NULL,
+    PRIMARY KEY(post_id, tag_id),
+    INDEX(tag_id),
+    FOREIGN KEY(post_id) REFERENCES posts(id) ON DELETE CASCADE,
+    FOREIGN KEY(tag_id) REFERENCES tags(id) ON DELETE CASCADE
+);
+-- old slugs of edited posts keep redirecting for good
+CREATE TABLE IF NOT EXISTS post_slug_redirects (
+    old_slug VARCHAR(90) PRIMARY KEY,
+    post_id INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(post_id) REFERENCES posts(id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS roles (
+    name VARCHAR(32) PRIMARY KEY
+);
+CREATE TABLE IF NOT EXISTS permissions (
+    name VARCHAR(64) PRIMARY KEY
+);
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role VARCHAR(32) NOT NULL,
+    permission VARCHAR(64) NOT NULL,
+    PRIMARY KEY(role, permission),
+    FOREIGN KEY(role) REFERENCES roles(name) ON DELETE CASCADE,
+    FOREIGN KEY(permission) REFERENCES permissions(name) ON DELETE CASCADE
 );
 CREATE TABLE IF NOT EXISTS users (
     id INT AUTO_INCREMENT PRIMARY KEY,
     email VARCHAR(255) NOT NULL UNIQUE,
     username VARCHAR(255) NOT NULL,
+    handle VARCHAR(30) NOT NULL UNIQUE,
+    display_name VARCHAR(100) NULL,
+    bio TEXT NULL,
+    website VARCHAR(255) NULL,
+    avatar_version CHAR(16) NULL,
     password TEXT NOT NULL,
-    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+    role VARCHAR(32) NOT NULL DEFAULT 'author',
+    email_verified_at TIMESTAMP NULL,
+    totp_secret VARCHAR(64) NULL,
+    totp_enabled_at TIMESTAMP NULL,
+    -- last accepted 30 second step, a code can only be used once
+    totp_last_step BIGINT NULL,
+    suspended_at TIMESTAMP NULL,
+    suspended_until TIMESTAMP NULL,
+    suspension_reason VARCHAR(500) NULL,
+    deleted_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(role) REFERENCES roles(name)
+);
+CREATE TABLE IF NOT EXISTS handle_redirects (
+    old_handle VARCHAR(30) PRIMARY KEY,
+    user_id INT NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS follows (
+    follower_id INT NOT NULL,
+    followee_id INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(follower_id, followee_id),
+    INDEX(followee_id),
+    FOREIGN KEY(follower_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY(followee_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS blocks (
+    blocker_id INT NOT NULL,
+    blocked_id INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(blocker_id, blocked_id),
+    INDEX(blocked_id),
+    FOREIGN KEY(blocker_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY(blocked_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS mutes (
+    muter_id INT NOT NULL,
+    muted_id INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(muter_id, muted_id),
+    FOREIGN KEY(muter_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY(muted_id) REFERENCES users(id) ON DELETE CASCADE
 );
 CREATE TABLE IF NOT EXISTS comments (
     id INT PRIMARY KEY AUTO_INCREMENT,
@@ -19,6 +122,126 @@ CREATE TABLE IF NOT EXISTS comments (
     author_id INt NOT NULL,
     body TEXT NOT NULL,
     created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP NULL,
+    INDEX(deleted_at),
+    FULLTEXT(body),
     FOREIGN KEY(post_id) REFERENCES posts(id) ON DELETE CASCADE,
-    FOREIGN KEY(author_id) REFERENCES users(id) ON DELETE CASCADE
-);
\ No newline at end of file
+    FOREIGN KEY(author_id) REFERENCES users(id) ON DELETE RESTRICT
+);
+-- every saved version of a post, oldest first
+CREATE TABLE IF NOT EXISTS post_revisions (
+    id INT PRIMARY KEY AUTO_INCREMENT,
+    post_id INT NOT NULL,
+    editor_id INT NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX(post_id, id),
+    FOREIGN KEY(post_id) REFERENCES posts(id) ON DELETE CASCADE,
+    FOREIGN KEY(editor_id) REFERENCES users(id) ON DELETE SET NULL
+);
+CREATE TABLE IF NOT EXISTS sessions (
+    id INT PRIMARY KEY AUTO_INCREMENT,
+    user_id INT NOT NULL,
+    refresh_token_hash CHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP NULL,
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS api_keys (
+    id INT PRIMARY KEY AUTO_INCREMENT,
+    user_id INT NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    key_prefix VARCHAR(16) NOT NULL,
+    key_hash CHAR(64) NOT NULL UNIQUE,
+    scopes VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NULL,
+    last_used_at TIMESTAMP NULL,
+    revoked_at TIMESTAMP NULL,
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS password_resets (
+    id INT PRIMARY KEY AUTO_INCREMENT,
+    user_id INT NOT NULL,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP NULL,
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id INT PRIMARY KEY AUTO_INCREMENT,
+    user_id INT NOT NULL,
+    code_hash CHAR(64) NOT NULL,
+    used_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+-- sign in challenges waiting for a second factor, each accepts a single good code
+CREATE TABLE IF NOT EXISTS two_factor_challenges (
+    jti CHAR(64) PRIMARY KEY,
+    user_id INT NOT NULL,
+    attempts INT NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP NOT NULL,
+    consumed_at TIMESTAMP NULL,
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS account_deletions (
+    user_id INT PRIMARY KEY,
+    mode VARCHAR(16) NOT NULL,
+    requested_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    scheduled_for TIMESTAMP NOT NULL,
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS oidc_states (
+    state CHAR(64) PRIMARY KEY,
+    provider VARCHAR(64) NOT NULL,
+    nonce CHAR(64) NOT NULL,
+    code_verifier CHAR(64) NOT NULL,
+    -- set when a signed in user links a provider to their account
+    link_user_id INT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    FOREIGN KEY(link_user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS user_identities (
+    id INT PRIMARY KEY AUTO_INCREMENT,
+    user_id INT NOT NULL,
+    provider VARCHAR(64) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    email VARCHAR(255) NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(provider, subject),
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS login_throttles (
+    scope VARCHAR(16) NOT NULL,
+    identifier VARCHAR(255) NOT NULL,
+    failed_count INT NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    locked_until TIMESTAMP NULL,
+    PRIMARY KEY(scope, identifier)
+);
+-- @block
+INSERT IGNORE INTO roles (name) VALUES
+    ('admin'), ('editor'), ('moderator'), ('author'), ('reader');
+INSERT IGNORE INTO permissions (name) VALUES
+    ('post.create'), ('post.update.own'), ('post.update.any'),
+    ('post.delete.own'), ('post.delete.any'),
+    ('comment.create'), ('comment.update.own'), ('comment.delete.own'),
+    ('comment.moderate'), ('user.manage'), ('taxonomy.manage');
+INSERT IGNORE INTO role_permissions (role, permission)
+    SELECT 'admin', name FROM permissions;
+INSERT IGNORE INTO role_permissions (role, permission) VALUES
+    ('editor', 'post.create'), ('editor', 'post.update.own'), ('editor', 'post.update.any'),
+    ('editor', 'post.delete.own'), ('editor', 'post.delete.any'),
+    ('editor', 'comment.create'), ('editor', 'comment.update.own'),
+    ('editor', 'comment.delete.own'), ('editor', 'comment.moderate'),
+    ('editor', 'taxonomy.manage'),
+    ('moderator', 'comment.create'), ('moderator', 'comment.update.own'),
+    ('moderator', 'comment.delete.own'), ('moderator', 'comment.moderate'),
+    ('author', 'post.create'), ('author', 'post.update.own'), ('author', 'post.delete.own'),
+    ('author', 'comment.create'), ('author', 'comment.update.own'), ('author', 'comment.delete.own'),
+    ('reader', 'comment.create'), ('reader', 'comment.update.own'), ('reader', 'comment.delete.own');
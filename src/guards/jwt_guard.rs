@@ -1,5 +1,12 @@
 use crate::auth::jwt::{verify_jwt, Claims};
-use rocket::http::{CookieJar, Status};
+use crate::auth::keys::Keyring;
+use crate::auth::session::is_session_active;
+use crate::auth::suspension::is_suspended;
+use crate::config::{AuthConfig, TokenSource};
+use crate::db::Db;
+use crate::models::error::AuthError;
+use jsonwebtoken::errors::ErrorKind;
+use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome};
 use rocket::Request;
 
@@ -7,25 +14,81 @@ pub struct JwtAuth {
     pub claims: Claims,
 }
 
+// find the raw token in the first configured source present in the request
+fn extract_token(request: &Request<'_>, sources: &[TokenSource]) -> Result<String, AuthError> {
+    for source in sources {
+        match source {
+            TokenSource::Header => {
+                if let Some(header) = request.headers().get_one("Authorization") {
+                    return match header.strip_prefix("Bearer ") {
+                        Some(token) if !token.trim().is_empty() => Ok(token.trim().to_string()),
+                        _ => Err(AuthError::Malformed),
+                    };
+                }
+            }
+            TokenSource::Cookie => {
+                if let Some(cookie) = request.cookies().get("auth_token") {
+                    return Ok(cookie.value().to_string());
+                }
+            }
+        }
+    }
+    Err(AuthError::Missing)
+}
+
+async fn authenticate(request: &Request<'_>) -> Result<Claims, (Status, AuthError)> {
+    let config = request
+        .rocket()
+        .state::<AuthConfig>()
+        .expect("auth config is managed");
+    let token = extract_token(request, &config.token_sources)
+        .map_err(|reason| (Status::Unauthorized, reason))?;
+
+    // Verify the JWT and extract claims
+    let keyring = request
+        .rocket()
+        .state::<Keyring>()
+        .expect("keyring is managed");
+    let decoded = verify_jwt(keyring, &token).map_err(|e| {
+        let reason = match e.kind() {
+            ErrorKind::ExpiredSignature => AuthError::Expired,
+            ErrorKind::InvalidSignature => AuthError::BadSignature,
+            _ => AuthError::Malformed,
+        };
+        (Status::Unauthorized, reason)
+    })?;
+
+    // Reject tokens whose session was revoked (sign-out) or has expired
+    let db_pool = request
+        .rocket()
+        .state::<Db>()
+        .expect("database pool is managed");
+    match is_session_active(db_pool, decoded.claims.sid).await {
+        Ok(true) => {}
+        Ok(false) => return Err((Status::Unauthorized, AuthError::Revoked)),
+        Err(_) => return Err((Status::InternalServerError, AuthError::Revoked)),
+    }
+
+    // Suspended and banned users keep their session but may not use it
+    let user_id = decoded.claims.sub.parse().unwrap_or_default();
+    match is_suspended(db_pool, user_id).await {
+        Ok(false) => Ok(decoded.claims),
+        Ok(true) => Err((Status::Forbidden, AuthError::Suspended)),
+        Err(_) => Err((Status::InternalServerError, AuthError::Suspended)),
+    }
+}
+
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for JwtAuth {
-    type Error = ();
+    type Error = AuthError;
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        let cookies: &CookieJar<'_> = request.cookies();
-
-        // Retrieve the JWT token from the "token" cookie
-        if let Some(cookie) = cookies.get("auth_token") {
-            let token = cookie.value();
-
-            // Verify the JWT and extract claims
-            if let Ok(decoded) = verify_jwt(token) {
-                return Outcome::Success(JwtAuth {
-                    claims: decoded.claims,
-                });
+        match authenticate(request).await {
+            Ok(claims) => Outcome::Success(JwtAuth { claims }),
+            Err((status, reason)) => {
+                // keep the reason around so the 401 catcher can report it
+                request.local_cache(|| Some(reason));
+                Outcome::Error((status, reason))
             }
         }
-
-        // If token is missing or invalid, return Unauthorized
-        Outcome::Error((Status::Unauthorized, ()))
     }
 }
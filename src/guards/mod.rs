@@ -1,2 +1,4 @@
 pub mod jwt_guard;
 pub mod role_guard;
+pub mod scope_guard;
+pub mod verified_guard;
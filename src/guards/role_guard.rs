@@ -1,30 +1,65 @@
+use std::marker::PhantomData;
+
 use crate::auth::jwt::Claims;
+use crate::auth::permissions::{has_permission, TAXONOMY_MANAGE, USER_MANAGE};
+use crate::db::Db;
+use crate::models::error::AuthError;
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome};
 use rocket::Request;
 
 use super::jwt_guard::JwtAuth;
 
-pub struct RoleAuth {
+pub trait Permission: Send + Sync + 'static {
+    const NAME: &'static str;
+}
+
+pub struct ManageUsers;
+
+impl Permission for ManageUsers {
+    const NAME: &'static str = USER_MANAGE;
+}
+
+pub struct ManageTaxonomy;
+
+impl Permission for ManageTaxonomy {
+    const NAME: &'static str = TAXONOMY_MANAGE;
+}
+
+// signed in user whose role grants permission `P`
+pub struct RoleAuth<P: Permission> {
     pub claims: Claims,
+    _permission: PhantomData<P>,
 }
 
 #[rocket::async_trait]
-impl<'r> FromRequest<'r> for RoleAuth {
-    type Error = ();
+impl<'r, P: Permission> FromRequest<'r> for RoleAuth<P> {
+    type Error = AuthError;
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
         // Use the JwtAuth guard to first validate the JWT
-        if let Outcome::Success(jwt_auth) = JwtAuth::from_request(request).await {
-            // Check if the user has the "admin" role
-            if jwt_auth.claims.role == "admin" {
-                return Outcome::Success(RoleAuth {
-                    claims: jwt_auth.claims,
-                });
+        let jwt_auth = match JwtAuth::from_request(request).await {
+            Outcome::Success(jwt_auth) => jwt_auth,
+            Outcome::Error(e) => return Outcome::Error(e),
+            Outcome::Forward(status) => return Outcome::Forward(status),
+        };
+
+        // Check the role of the user against the role_permissions table
+        let db_pool = request
+            .rocket()
+            .state::<Db>()
+            .expect("database pool is managed");
+        let user_id = jwt_auth.claims.sub.parse().expect("faild to parse user id");
+        match has_permission(db_pool, user_id, P::NAME).await {
+            Ok(true) => Outcome::Success(RoleAuth {
+                claims: jwt_auth.claims,
+                _permission: PhantomData,
+            }),
+            Ok(false) => {
+                request.local_cache(|| Some(AuthError::MissingPermission));
+                Outcome::Error((Status::Forbidden, AuthError::MissingPermission))
             }
+            Err(_) => Outcome::Error((Status::InternalServerError, AuthError::MissingPermission)),
         }
-
-        // If the role is not "admin", return Forbidden
-        Outcome::Error((Status::Forbidden, ()))
     }
 }
@@ -0,0 +1,95 @@
+use std::marker::PhantomData;
+
+use crate::auth::api_key::{authenticate_api_key, API_KEY_PREFIX};
+use crate::auth::suspension::is_suspended;
+use crate::db::Db;
+use crate::models::error::AuthError;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+
+use super::jwt_guard::JwtAuth;
+
+pub trait Scope: Send + Sync + 'static {
+    const NAME: &'static str;
+}
+
+pub struct PostsRead;
+pub struct PostsWrite;
+pub struct CommentsRead;
+pub struct CommentsWrite;
+
+impl Scope for PostsRead {
+    const NAME: &'static str = "posts:read";
+}
+impl Scope for PostsWrite {
+    const NAME: &'static str = "posts:write";
+}
+impl Scope for CommentsRead {
+    const NAME: &'static str = "comments:read";
+}
+impl Scope for CommentsWrite {
+    const NAME: &'static str = "comments:write";
+}
+
+// Accepts either a signed in user (JwtAuth) or an api key granted scope `S`
+pub struct ScopeAuth<S: Scope> {
+    pub user_id: i64,
+    _scope: PhantomData<S>,
+}
+
+impl<S: Scope> ScopeAuth<S> {
+    fn new(user_id: i64) -> Self {
+        ScopeAuth {
+            user_id,
+            _scope: PhantomData,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, S: Scope> FromRequest<'r> for ScopeAuth<S> {
+    type Error = AuthError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let api_key = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .filter(|token| token.starts_with(API_KEY_PREFIX));
+
+        // Without an api key fall back to the session, which is not scoped
+        let Some(api_key) = api_key else {
+            return match JwtAuth::from_request(request).await {
+                Outcome::Success(jwt_auth) => Outcome::Success(ScopeAuth::new(
+                    jwt_auth.claims.sub.parse().expect("faild to parse user id"),
+                )),
+                Outcome::Error(e) => Outcome::Error(e),
+                Outcome::Forward(status) => Outcome::Forward(status),
+            };
+        };
+
+        let db_pool = request
+            .rocket()
+            .state::<Db>()
+            .expect("database pool is managed");
+        let reason = match authenticate_api_key(db_pool, api_key).await {
+            Ok(Some(principal)) if principal.scopes.iter().any(|s| s == S::NAME) => {
+                match is_suspended(db_pool, principal.user_id).await {
+                    Ok(false) => return Outcome::Success(ScopeAuth::new(principal.user_id)),
+                    Ok(true) => (Status::Forbidden, AuthError::Suspended),
+                    Err(_) => {
+                        return Outcome::Error((Status::InternalServerError, AuthError::Suspended))
+                    }
+                }
+            }
+            Ok(Some(_)) => (Status::Forbidden, AuthError::InsufficientScope),
+            Ok(None) => (Status::Unauthorized, AuthError::InvalidApiKey),
+            Err(_) => {
+                return Outcome::Error((Status::InternalServerError, AuthError::InvalidApiKey))
+            }
+        };
+        request.local_cache(|| Some(reason.1));
+        Outcome::Error(reason)
+    }
+}
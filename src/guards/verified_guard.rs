@@ -0,0 +1,62 @@
+use crate::config::AuthConfig;
+use crate::db::Db;
+use crate::models::error::AuthError;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+
+use super::scope_guard::{Scope, ScopeAuth};
+
+// ScopeAuth that also requires a verified email when REQUIRE_EMAIL_VERIFICATION is set
+pub struct VerifiedAuth<S: Scope> {
+    pub user_id: i64,
+    _scope: std::marker::PhantomData<S>,
+}
+
+#[rocket::async_trait]
+impl<'r, S: Scope> FromRequest<'r> for VerifiedAuth<S> {
+    type Error = AuthError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let user = match ScopeAuth::<S>::from_request(request).await {
+            Outcome::Success(user) => user,
+            Outcome::Error(e) => return Outcome::Error(e),
+            Outcome::Forward(status) => return Outcome::Forward(status),
+        };
+
+        let config = request
+            .rocket()
+            .state::<AuthConfig>()
+            .expect("auth config is managed");
+        if config.require_verified_email {
+            let db_pool = request
+                .rocket()
+                .state::<Db>()
+                .expect("database pool is managed");
+            let verified = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM users WHERE id = ? AND email_verified_at IS NOT NULL)",
+                user.user_id
+            )
+            .fetch_one(db_pool)
+            .await;
+            match verified {
+                Ok(0) => {
+                    request.local_cache(|| Some(AuthError::EmailNotVerified));
+                    return Outcome::Error((Status::Forbidden, AuthError::EmailNotVerified));
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    return Outcome::Error((
+                        Status::InternalServerError,
+                        AuthError::EmailNotVerified,
+                    ))
+                }
+            }
+        }
+
+        Outcome::Success(VerifiedAuth {
+            user_id: user.user_id,
+            _scope: std::marker::PhantomData,
+        })
+    }
+}
@@ -0,0 +1,177 @@
+use rocket::{request::FromParam, response::Redirect, serde::json::Json};
+use sqlx::MySqlConnection;
+
+use crate::{auth::token::generate_token, db::Db};
+
+pub const MIN_HANDLE_LENGTH: usize = 3;
+pub const MAX_HANDLE_LENGTH: usize = 30;
+// how long an old handle keeps redirecting to the new one
+pub const HANDLE_REDIRECT_DAYS: i64 = 30;
+
+// would clash with our own routes or impersonate staff
+const RESERVED_HANDLES: [&str; 26] = [
+    "about",
+    "admin",
+    "administrator",
+    "api",
+    "api-keys",
+    "auth",
+    "comment",
+    "comments",
+    "feed",
+    "help",
+    "login",
+    "logout",
+    "me",
+    "moderator",
+    "post",
+    "posts",
+    "root",
+    "search",
+    "settings",
+    "sign-in",
+    "sign-out",
+    "sign-up",
+    "staff",
+    "support",
+    "system",
+    "users",
+];
+
+// handles are compared lowercased and may be written with a leading @
+pub fn normalize_handle(handle: &str) -> String {
+    handle.trim().trim_start_matches('@').to_lowercase()
+}
+
+pub fn validate_handle(handle: &str) -> Result<(), String> {
+    if handle.len() < MIN_HANDLE_LENGTH || handle.len() > MAX_HANDLE_LENGTH {
+        return Err(format!(
+            "Handle must be between {} and {} characters",
+            MIN_HANDLE_LENGTH, MAX_HANDLE_LENGTH
+        ));
+    }
+    if !handle
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    {
+        return Err("Handle may only contain lowercase letters, digits, '_' and '-'".to_string());
+    }
+    if !handle.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err("Handle must start with a letter or digit".to_string());
+    }
+    // anonymised accounts are renamed to deleted-<id>, and an all digit handle would be
+    // read as a user id by /users/<id>
+    if RESERVED_HANDLES.contains(&handle)
+        || handle.starts_with("deleted-")
+        || handle.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err("This handle is reserved".to_string());
+    }
+    Ok(())
+}
+
+// a handle is taken by another user's current handle or by their recent old one
+pub async fn is_handle_available(
+    conn: &mut MySqlConnection,
+    handle: &str,
+    user_id: Option<i64>,
+) -> Result<bool, sqlx::Error> {
+    let user_id = user_id.unwrap_or(0);
+    let taken = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE handle = ? AND id <> ?)
+         OR EXISTS(SELECT 1 FROM handle_redirects WHERE old_handle = ? AND user_id <> ? AND expires_at > NOW())",
+        handle,
+        user_id,
+        handle,
+        user_id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(taken == 0)
+}
+
+// derive a free handle from a display username, used when none was chosen
+pub async fn generate_handle(
+    conn: &mut MySqlConnection,
+    username: &str,
+) -> Result<String, sqlx::Error> {
+    let mut base = String::new();
+    for c in username.trim().to_lowercase().chars() {
+        let c = if c.is_ascii_alphanumeric() || c == '-' {
+            c
+        } else {
+            '_'
+        };
+        if !(c == '_' && base.ends_with('_')) {
+            base.push(c);
+        }
+    }
+    let mut base: String = base
+        .trim_matches(|c| c == '_' || c == '-')
+        .chars()
+        .take(MAX_HANDLE_LENGTH - 7)
+        .collect();
+    if base.len() < MIN_HANDLE_LENGTH {
+        base = "user".to_string();
+    }
+
+    let mut candidates = vec![base.clone()];
+    candidates.extend((2..10).map(|n| format!("{}_{}", base, n)));
+    for candidate in candidates {
+        if validate_handle(&candidate).is_ok()
+            && is_handle_available(&mut *conn, &candidate, None).await?
+        {
+            return Ok(candidate);
+        }
+    }
+    // crowded base, fall back to a random suffix
+    Ok(format!("{}_{}", base, &generate_token()[..6]))
+}
+
+pub enum HandleLookup {
+    Current(i64),
+    // the handle was changed recently, holds the current one
+    Moved(String),
+}
+
+pub async fn resolve_handle(
+    db_pool: &Db,
+    handle: &str,
+) -> Result<Option<HandleLookup>, sqlx::Error> {
+    let handle = normalize_handle(handle);
+    if let Some(user_id) = sqlx::query_scalar!("SELECT id FROM users WHERE handle = ?", handle)
+        .fetch_optional(db_pool)
+        .await?
+    {
+        return Ok(Some(HandleLookup::Current(user_id as i64)));
+    }
+    let moved = sqlx::query_scalar!(
+        "SELECT u.handle FROM handle_redirects r JOIN users u ON u.id = r.user_id
+         WHERE r.old_handle = ? AND r.expires_at > NOW()",
+        handle
+    )
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(moved.map(HandleLookup::Moved))
+}
+
+// lookups through an old handle answer with a permanent redirect to the current one
+#[derive(Responder)]
+pub enum HandleResponse<T> {
+    Found(Json<T>),
+    Moved(Redirect),
+}
+
+// path segment of the form `@handle`, anything else forwards to the next route
+pub struct AtHandle(pub String);
+
+impl<'a> FromParam<'a> for AtHandle {
+    type Error = &'a str;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        match param.strip_prefix('@') {
+            Some(handle) if !handle.is_empty() => Ok(AtHandle(normalize_handle(handle))),
+            _ => Err(param),
+        }
+    }
+}
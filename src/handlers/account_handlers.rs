@@ -0,0 +1,204 @@
+use blog_api::timestamp_to_datetime;
+use chrono::{DateTime, Utc};
+use rocket::{http::Status, response::status, serde::json::Json};
+
+use crate::{
+    account::{DeletionMode, ACCOUNT_DELETION_COOL_OFF_DAYS},
+    auth::password::verify_password,
+    db::Db,
+    guards::jwt_guard::JwtAuth,
+    handlers::user::load_profile,
+    models::{
+        account::{
+            AccountExport, AccountExportDownload, DeletionRequest, LinkedIdentity,
+            ScheduledDeletion,
+        },
+        api_key::ApiKey,
+        comment::Comment,
+        error::{db_error, ResponseError},
+        post::{post_from_row, Post},
+    },
+    taxonomy::attach_tags,
+};
+
+fn no_deletion_scheduled() -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::NotFound,
+        Json(ResponseError {
+            error: "No account deletion is scheduled".to_string(),
+        }),
+    )
+}
+
+#[get("/me/export")]
+pub async fn export_account(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+) -> Result<AccountExportDownload, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let profile = load_profile(db_pool, user_id).await?;
+
+    let mut posts: Vec<Post> = sqlx::query!(
+        "SELECT * FROM posts WHERE author_id = ? ORDER BY created_at",
+        user_id
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .iter()
+    .map(|row| post_from_row!(row))
+    .collect();
+    attach_tags(db_pool.inner(), &mut posts)
+        .await
+        .map_err(db_error)?;
+
+    let comments = sqlx::query!(
+        "SELECT * FROM comments WHERE author_id = ? ORDER BY created_at",
+        user_id
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .iter()
+    .map(|row| Comment {
+        id: row.id as i64,
+        post_id: row.post_id as i64,
+        author_id: row.author_id as i64,
+        body: row.body.clone(),
+        created_at: timestamp_to_datetime!(row).expect("faild to parse datatime"),
+    })
+    .collect();
+
+    let api_keys = sqlx::query!(
+        "SELECT * FROM api_keys WHERE user_id = ? ORDER BY created_at",
+        user_id
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .iter()
+    .map(|row| ApiKey {
+        id: row.id,
+        name: row.name.clone(),
+        key_prefix: row.key_prefix.clone(),
+        scopes: row.scopes.split(' ').map(str::to_string).collect(),
+        created_at: timestamp_to_datetime!(row).expect("faild to parse datatime"),
+        expires_at: timestamp_to_datetime!(row, expires_at),
+        last_used_at: timestamp_to_datetime!(row, last_used_at),
+    })
+    .collect();
+
+    let identities = sqlx::query!(
+        "SELECT provider, email, created_at FROM user_identities WHERE user_id = ?",
+        user_id
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .iter()
+    .map(|row| LinkedIdentity {
+        provider: row.provider.clone(),
+        email: row.email.clone(),
+        created_at: timestamp_to_datetime!(row).expect("faild to parse datatime"),
+    })
+    .collect();
+
+    Ok(AccountExportDownload::new(AccountExport {
+        exported_at: Utc::now(),
+        profile,
+        posts,
+        comments,
+        api_keys,
+        identities,
+    }))
+}
+
+async fn load_scheduled_deletion(
+    db_pool: &rocket::State<Db>,
+    user_id: i64,
+) -> Result<Option<ScheduledDeletion>, status::Custom<Json<ResponseError>>> {
+    let record = sqlx::query!(
+        "SELECT mode, requested_at, scheduled_for FROM account_deletions WHERE user_id = ?",
+        user_id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(record.map(|record| ScheduledDeletion {
+        mode: DeletionMode::parse(&record.mode).unwrap_or(DeletionMode::Delete),
+        requested_at: timestamp_to_datetime!(record, requested_at).expect("faild to parse date"),
+        scheduled_for: timestamp_to_datetime!(record, scheduled_for).expect("faild to parse date"),
+    }))
+}
+
+#[get("/me/deletion")]
+pub async fn get_account_deletion(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+) -> Result<Json<ScheduledDeletion>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    load_scheduled_deletion(db_pool, user_id)
+        .await?
+        .map(Json)
+        .ok_or_else(no_deletion_scheduled)
+}
+
+// nothing is removed until the cool-off period is over, the user can still cancel
+#[post("/me/deletion", data = "<request>")]
+pub async fn schedule_account_deletion(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    request: Json<DeletionRequest>,
+) -> Result<status::Custom<Json<ScheduledDeletion>>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let password_hash = sqlx::query_scalar!("SELECT password FROM users WHERE id = ?", user_id)
+        .fetch_optional(db_pool.inner())
+        .await
+        .map_err(db_error)?
+        .flatten();
+    let password_matches = password_hash
+        .as_ref()
+        .is_some_and(|password_hash| verify_password(&request.password, password_hash).is_ok());
+    if !password_matches {
+        return Err(status::Custom(
+            Status::Forbidden,
+            Json(ResponseError {
+                error: "Password is incorrect".to_string(),
+            }),
+        ));
+    }
+
+    // asking again restarts the cool-off with the newly chosen mode
+    sqlx::query!(
+        "INSERT INTO account_deletions (user_id, mode, scheduled_for) VALUES (?, ?, NOW() + INTERVAL ? DAY)
+         ON DUPLICATE KEY UPDATE mode = VALUES(mode), requested_at = NOW(), scheduled_for = VALUES(scheduled_for)",
+        user_id,
+        request.mode.as_str(),
+        ACCOUNT_DELETION_COOL_OFF_DAYS
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let scheduled = load_scheduled_deletion(db_pool, user_id)
+        .await?
+        .ok_or_else(no_deletion_scheduled)?;
+    Ok(status::Custom(Status::Accepted, Json(scheduled)))
+}
+
+#[delete("/me/deletion")]
+pub async fn cancel_account_deletion(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let result = sqlx::query!("DELETE FROM account_deletions WHERE user_id = ?", user_id)
+        .execute(db_pool.inner())
+        .await
+        .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err(no_deletion_scheduled());
+    }
+    Ok(status::Custom(Status::NoContent, ()))
+}
@@ -0,0 +1,300 @@
+use blog_api::timestamp_to_datetime;
+use chrono::{DateTime, Utc};
+use rocket::{http::Status, response::status, serde::json::Json};
+
+use crate::{
+    account::hard_delete_user,
+    auth::{session::revoke_all_sessions, throttle::clear_failed_sign_ins},
+    avatar::avatar_dir,
+    db::Db,
+    guards::role_guard::{ManageUsers, RoleAuth},
+    models::{
+        admin::{AdminUser, ChangeRole, Suspension, UserSearch},
+        error::{db_error, ResponseError},
+        PagedResponse,
+    },
+    storage::Store,
+};
+
+fn user_not_found() -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::NotFound,
+        Json(ResponseError {
+            error: "User not found".to_string(),
+        }),
+    )
+}
+
+// admins must not lock themselves out by acting on their own account
+fn reject_self(
+    admin: &RoleAuth<ManageUsers>,
+    id: i64,
+) -> Result<(), status::Custom<Json<ResponseError>>> {
+    if admin.claims.sub == id.to_string() {
+        return Err(status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "You cannot do this to your own account".to_string(),
+            }),
+        ));
+    }
+    Ok(())
+}
+
+async fn load_user(
+    db_pool: &rocket::State<Db>,
+    id: i64,
+) -> Result<AdminUser, status::Custom<Json<ResponseError>>> {
+    let record = sqlx::query!(
+        "SELECT id, username, handle, email, role, email_verified_at, suspended_at, suspended_until, suspension_reason, created_at
+         FROM users WHERE id = ?",
+        id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(user_not_found)?;
+
+    Ok(AdminUser {
+        id: record.id,
+        username: record.username,
+        handle: record.handle,
+        email: record.email,
+        role: record.role,
+        email_verified_at: timestamp_to_datetime!(record, email_verified_at),
+        suspended_at: timestamp_to_datetime!(record, suspended_at),
+        suspended_until: timestamp_to_datetime!(record, suspended_until),
+        suspension_reason: record.suspension_reason,
+        created_at: timestamp_to_datetime!(record).expect("faild to parse date"),
+    })
+}
+
+// search matches email, username and handle
+#[get("/users?<search..>")]
+pub async fn get_users(
+    db_pool: &rocket::State<Db>,
+    _admin: RoleAuth<ManageUsers>,
+    search: UserSearch,
+) -> Result<Json<PagedResponse<AdminUser>>, status::Custom<Json<ResponseError>>> {
+    let page = search.page.unwrap_or(1).max(1) as i64;
+    let size = search.size.unwrap_or(20).clamp(1, 100) as i64;
+    let offset = (page - 1) * size;
+    // escape LIKE wildcards so the search term is matched literally
+    let pattern = search.q.as_ref().map(|q| {
+        format!(
+            "%{}%",
+            q.trim()
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        )
+    });
+
+    let query = sqlx::query!(
+        "SELECT id, username, handle, email, role, email_verified_at, suspended_at, suspended_until, suspension_reason, created_at
+         FROM users WHERE ? IS NULL OR email LIKE ? OR username LIKE ? OR handle LIKE ?
+         ORDER BY id LIMIT ? OFFSET ?",
+        pattern,
+        pattern,
+        pattern,
+        pattern,
+        size,
+        offset
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let total_items = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM users WHERE ? IS NULL OR email LIKE ? OR username LIKE ? OR handle LIKE ?",
+        pattern,
+        pattern,
+        pattern,
+        pattern
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    let total_pages = if total_items > 0 {
+        (total_items + size - 1) / size
+    } else {
+        0
+    };
+
+    let users = query
+        .into_iter()
+        .map(|record| AdminUser {
+            id: record.id,
+            username: record.username,
+            handle: record.handle,
+            email: record.email,
+            role: record.role,
+            email_verified_at: timestamp_to_datetime!(record, email_verified_at),
+            suspended_at: timestamp_to_datetime!(record, suspended_at),
+            suspended_until: timestamp_to_datetime!(record, suspended_until),
+            suspension_reason: record.suspension_reason,
+            created_at: timestamp_to_datetime!(record).expect("faild to parse date"),
+        })
+        .collect();
+
+    Ok(Json(PagedResponse {
+        current_page: page,
+        page_size: size,
+        total_items,
+        total_pages,
+        data: users,
+    }))
+}
+
+#[get("/users/<id>")]
+pub async fn get_user(
+    db_pool: &rocket::State<Db>,
+    _admin: RoleAuth<ManageUsers>,
+    id: i64,
+) -> Result<Json<AdminUser>, status::Custom<Json<ResponseError>>> {
+    Ok(Json(load_user(db_pool, id).await?))
+}
+
+#[put("/users/<id>/role", data = "<request>")]
+pub async fn change_role(
+    db_pool: &rocket::State<Db>,
+    admin: RoleAuth<ManageUsers>,
+    id: i64,
+    request: Json<ChangeRole>,
+) -> Result<Json<AdminUser>, status::Custom<Json<ResponseError>>> {
+    reject_self(&admin, id)?;
+    let role_exists = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM roles WHERE name = ?)",
+        request.role
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    if role_exists == 0 {
+        return Err(status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: format!("Unknown role: {}", request.role),
+            }),
+        ));
+    }
+
+    // permissions are looked up per request, so the new role applies right away
+    sqlx::query!("UPDATE users SET role = ? WHERE id = ?", request.role, id)
+        .execute(db_pool.inner())
+        .await
+        .map_err(db_error)?;
+    Ok(Json(load_user(db_pool, id).await?))
+}
+
+#[post("/users/<id>/suspension", data = "<request>")]
+pub async fn suspend_user(
+    db_pool: &rocket::State<Db>,
+    admin: RoleAuth<ManageUsers>,
+    id: i64,
+    request: Json<Suspension>,
+) -> Result<Json<AdminUser>, status::Custom<Json<ResponseError>>> {
+    reject_self(&admin, id)?;
+    let reason = request.reason.trim();
+    if reason.is_empty() {
+        return Err(status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "A reason is required".to_string(),
+            }),
+        ));
+    }
+    if request.expires_in_days.is_some_and(|days| days < 1) {
+        return Err(status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "expires_in_days must be at least 1".to_string(),
+            }),
+        ));
+    }
+
+    let mut tx = db_pool.begin().await.map_err(db_error)?;
+    // a NULL interval leaves suspended_until NULL, which makes it a ban
+    let result = sqlx::query!(
+        "UPDATE users SET suspended_at = NOW(), suspended_until = NOW() + INTERVAL ? DAY, suspension_reason = ? WHERE id = ?",
+        request.expires_in_days,
+        reason,
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err(user_not_found());
+    }
+    // end every session now, JwtAuth would reject them anyway
+    revoke_all_sessions(&mut *tx, id).await.map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(Json(load_user(db_pool, id).await?))
+}
+
+#[delete("/users/<id>/suspension")]
+pub async fn lift_suspension(
+    db_pool: &rocket::State<Db>,
+    _admin: RoleAuth<ManageUsers>,
+    id: i64,
+) -> Result<Json<AdminUser>, status::Custom<Json<ResponseError>>> {
+    sqlx::query!(
+        "UPDATE users SET suspended_at = NULL, suspended_until = NULL, suspension_reason = NULL WHERE id = ?",
+        id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    Ok(Json(load_user(db_pool, id).await?))
+}
+
+#[post("/users/<id>/sign-out")]
+pub async fn sign_out_user(
+    db_pool: &rocket::State<Db>,
+    _admin: RoleAuth<ManageUsers>,
+    id: i64,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    load_user(db_pool, id).await?;
+    revoke_all_sessions(db_pool.inner(), id)
+        .await
+        .map_err(db_error)?;
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+#[delete("/users/<id>")]
+pub async fn delete_user(
+    db_pool: &rocket::State<Db>,
+    storage: &rocket::State<Store>,
+    admin: RoleAuth<ManageUsers>,
+    id: i64,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    reject_self(&admin, id)?;
+    let mut tx = db_pool.begin().await.map_err(db_error)?;
+    if !hard_delete_user(&mut *tx, id).await.map_err(db_error)? {
+        return Err(user_not_found());
+    }
+    tx.commit().await.map_err(db_error)?;
+    let _ = storage.delete(&avatar_dir(id)).await;
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+// lift a sign in lockout before it runs out on its own
+#[post("/users/<id>/unlock")]
+pub async fn unlock_user(
+    db_pool: &rocket::State<Db>,
+    _admin: RoleAuth<ManageUsers>,
+    id: i64,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let email = sqlx::query_scalar!("SELECT email FROM users WHERE id = ?", id)
+        .fetch_optional(db_pool.inner())
+        .await
+        .map_err(db_error)?
+        .ok_or_else(user_not_found)?;
+
+    clear_failed_sign_ins(db_pool.inner(), &email.trim().to_lowercase())
+        .await
+        .map_err(db_error)?;
+    Ok(status::Custom(Status::NoContent, ()))
+}
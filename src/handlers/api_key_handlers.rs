@@ -0,0 +1,175 @@
+use blog_api::timestamp_to_datetime;
+use chrono::{DateTime, Utc};
+use rocket::{http::Status, response::status, serde::json::Json};
+
+use crate::{
+    auth::{api_key::generate_api_key, token::hash_token},
+    db::Db,
+    guards::jwt_guard::JwtAuth,
+    models::{
+        api_key::{ApiKey, CreatedApiKey, NewApiKey, SCOPES},
+        error::ResponseError,
+    },
+};
+
+// api keys can only be managed from a signed in session, never with another key
+#[post("/api-keys", data = "<new_key>")]
+pub async fn create_api_key(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    new_key: Json<NewApiKey>,
+) -> Result<Json<CreatedApiKey>, status::Custom<Json<ResponseError>>> {
+    if new_key.scopes.is_empty() {
+        return Err(status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "At least one scope is required".to_string(),
+            }),
+        ));
+    }
+    if let Some(scope) = new_key
+        .scopes
+        .iter()
+        .find(|scope| !SCOPES.contains(&scope.as_str()))
+    {
+        return Err(status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: format!("Unknown scope: {}", scope),
+            }),
+        ));
+    }
+    if new_key.expires_in_days.is_some_and(|days| days < 1) {
+        return Err(status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "expires_in_days must be at least 1".to_string(),
+            }),
+        ));
+    }
+
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let key = generate_api_key();
+    let key_prefix: String = key.chars().take(12).collect();
+    let scopes = new_key.scopes.join(" ");
+
+    let query = sqlx::query!(
+        "INSERT INTO api_keys (user_id, name, key_prefix, key_hash, scopes, expires_at)
+         VALUES (?, ?, ?, ?, ?, NOW() + INTERVAL ? DAY)",
+        user_id,
+        new_key.name,
+        key_prefix,
+        hash_token(&key),
+        scopes,
+        new_key.expires_in_days
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Database Error".to_string(),
+            }),
+        )
+    })?;
+
+    let record = sqlx::query!(
+        "SELECT created_at, expires_at FROM api_keys WHERE id = ?",
+        query.last_insert_id()
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Database Error".to_string(),
+            }),
+        )
+    })?;
+
+    Ok(Json(CreatedApiKey {
+        api_key: ApiKey {
+            id: query.last_insert_id() as i32,
+            name: new_key.name.clone(),
+            key_prefix,
+            scopes: new_key.scopes.clone(),
+            created_at: timestamp_to_datetime!(record).unwrap_or_else(Utc::now),
+            expires_at: timestamp_to_datetime!(record, expires_at),
+            last_used_at: None,
+        },
+        key,
+    }))
+}
+
+#[get("/api-keys")]
+pub async fn get_api_keys(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+) -> Result<Json<Vec<ApiKey>>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let query = sqlx::query!(
+        "SELECT * FROM api_keys WHERE user_id = ? AND revoked_at IS NULL ORDER BY created_at DESC",
+        user_id
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Database Error".to_string(),
+            }),
+        )
+    })?;
+
+    let api_keys: Vec<ApiKey> = query
+        .iter()
+        .map(|row| ApiKey {
+            id: row.id,
+            name: row.name.clone(),
+            key_prefix: row.key_prefix.clone(),
+            scopes: row.scopes.split(' ').map(str::to_string).collect(),
+            created_at: timestamp_to_datetime!(row).expect("faild to parse datatime"),
+            expires_at: timestamp_to_datetime!(row, expires_at),
+            last_used_at: timestamp_to_datetime!(row, last_used_at),
+        })
+        .collect();
+
+    Ok(Json(api_keys))
+}
+
+#[delete("/api-keys/<id>")]
+pub async fn revoke_api_key(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    id: i64,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let result = sqlx::query!(
+        "UPDATE api_keys SET revoked_at = NOW() WHERE id = ? AND user_id = ? AND revoked_at IS NULL",
+        id,
+        user_id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Database Error".to_string(),
+            }),
+        )
+    })?;
+    if result.rows_affected() == 0 {
+        return Err(status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "API key not found".to_string(),
+            }),
+        ));
+    }
+
+    Ok(status::Custom(Status::NoContent, ()))
+}
@@ -1,12 +1,27 @@
 use crate::{
     auth::{
-        jwt::generate_jwt,
-        password::{hash_password, verify_password},
+        jwt::{
+            generate_email_verification_token, generate_jwt, generate_two_factor_challenge,
+            verify_email_verification_token,
+        },
+        keys::Keyring,
+        password::{hash_password, verify_dummy_password, verify_password},
+        session::{create_session, revoke_all_sessions, revoke_session, rotate_session},
+        suspension::is_suspended,
+        throttle::{clear_failed_sign_ins, lockout_remaining, record_failed_sign_in},
+        token::{generate_token, hash_token},
+        totp::store_challenge,
     },
+    avatar::avatar_urls,
+    config::app_url,
     db::Db,
+    guards::jwt_guard::JwtAuth,
+    handle::{generate_handle, is_handle_available, normalize_handle, validate_handle},
+    mail::{Email, Mail},
     models::{
-        error::ResponseError,
-        user::{NewUser, User, UserCredential},
+        error::{db_error, ResponseError, SignInError, TooManyRequests},
+        two_factor::TwoFactorChallenge,
+        user::{ForgotPassword, NewUser, ResetPassword, SignInResponse, User, UserCredential},
     },
 };
 use blog_api::timestamp_to_datetime;
@@ -16,97 +31,239 @@ use rocket::{
     response::status,
     serde::json::Json,
 };
+use std::net::IpAddr;
 
-#[post("/sign-in", data = "<user_credential>")]
-pub async fn sign_in(
+const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+
+// open a session for the user and hand out the access and refresh cookies
+pub async fn start_session(
     db_pool: &rocket::State<Db>,
+    keyring: &Keyring,
     cookie: &CookieJar<'_>,
-    user_credential: Json<UserCredential>,
-) -> Result<Json<User>, status::Custom<Json<ResponseError>>> {
-    // check if user exits
-    let result: bool = sqlx::query!(
-        "SELECT EXISTS (SELECT 1 FROM users WHERE email = ?) AS user_exists",
-        user_credential.email
-    )
-    .fetch_one(db_pool.inner())
-    .await
-    .map_err(|_| {
+    user_id: i64,
+    role: &String,
+) -> Result<(), status::Custom<Json<ResponseError>>> {
+    let suspended = is_suspended(db_pool.inner(), user_id)
+        .await
+        .map_err(db_error)?;
+    if suspended {
+        return Err(status::Custom(
+            Status::Forbidden,
+            Json(ResponseError {
+                error: "This account is suspended".to_string(),
+            }),
+        ));
+    }
+    let (session_id, refresh_token) = create_session(db_pool.inner(), user_id)
+        .await
+        .map_err(db_error)?;
+    set_auth_cookies(keyring, cookie, user_id, role, session_id, refresh_token)
+}
+
+// first half of a sign in for users with 2FA, the session starts at /auth/2fa/verify
+pub async fn start_two_factor_challenge(
+    db_pool: &Db,
+    keyring: &Keyring,
+    user_id: i64,
+) -> Result<TwoFactorChallenge, status::Custom<Json<ResponseError>>> {
+    let jti = generate_token();
+    store_challenge(db_pool, &jti, user_id)
+        .await
+        .map_err(db_error)?;
+    let challenge_token = generate_two_factor_challenge(keyring, &user_id.to_string(), &jti)
+        .map_err(|_| {
+            status::Custom(
+                Status::InternalServerError,
+                Json(ResponseError {
+                    error: "faild to issue jwt token".to_string(),
+                }),
+            )
+        })?;
+    Ok(TwoFactorChallenge {
+        two_factor_required: true,
+        challenge_token,
+    })
+}
+
+fn set_auth_cookies(
+    keyring: &Keyring,
+    cookie: &CookieJar<'_>,
+    user_id: i64,
+    role: &String,
+    session_id: i64,
+    refresh_token: String,
+) -> Result<(), status::Custom<Json<ResponseError>>> {
+    let token = generate_jwt(keyring, &user_id.to_string(), role, session_id).map_err(|_| {
         status::Custom(
             Status::InternalServerError,
             Json(ResponseError {
-                error: "Database Error".to_string(),
+                error: "faild to issue jwt token".to_string(),
             }),
         )
-    })?
-    .user_exists
-        != 0;
-    // Handel error if user does not exists
-    if !result {
-        return Err(status::Custom(
-            Status::Unauthorized,
+    })?;
+    cookie.add(Cookie::build(("auth_token", token)).http_only(true));
+    // the refresh token is only ever sent to the auth routes
+    cookie.add(
+        Cookie::build(("refresh_token", refresh_token))
+            .path("/auth")
+            .http_only(true),
+    );
+    Ok(())
+}
+
+async fn send_verification_email(
+    keyring: &Keyring,
+    mailer: &rocket::State<Mail>,
+    user_id: i64,
+    email: &String,
+) -> Result<(), status::Custom<Json<ResponseError>>> {
+    let token =
+        generate_email_verification_token(keyring, &user_id.to_string(), email).map_err(|_| {
+            status::Custom(
+                Status::InternalServerError,
+                Json(ResponseError {
+                    error: "faild to issue verification token".to_string(),
+                }),
+            )
+        })?;
+    let email = Email {
+        to: email.clone(),
+        subject: "Verify your email address".to_string(),
+        body: format!(
+            "Confirm your email address by opening the link below.\n\n{}/auth/verify-email?token={}",
+            app_url(),
+            token
+        ),
+    };
+    mailer.send(&email).await.map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
             Json(ResponseError {
-                error: "Invalid email or password".to_string(),
+                error: "Failed to send email".to_string(),
             }),
-        ));
+        )
+    })
+}
+
+#[post("/sign-in", data = "<user_credential>")]
+pub async fn sign_in(
+    db_pool: &rocket::State<Db>,
+    keyring: &rocket::State<Keyring>,
+    cookie: &CookieJar<'_>,
+    client_ip: Option<IpAddr>,
+    user_credential: Json<UserCredential>,
+) -> Result<Json<SignInResponse>, SignInError> {
+    let email = user_credential.email.trim().to_lowercase();
+    let ip = client_ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+
+    // refuse to even look at the password while the account or client is locked out
+    if let Some(retry_after) = lockout_remaining(db_pool.inner(), &email, &ip)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(SignInError::TooManyRequests(TooManyRequests::new(
+            "Too many failed sign in attempts, try again later",
+            retry_after,
+        )));
     }
+
     // get user data
     let record = sqlx::query!("SELECT * FROM users WHERE email = ?", user_credential.email)
-        .fetch_one(db_pool.inner())
+        .fetch_optional(db_pool.inner())
         .await
-        .map_err(|_| {
-            status::Custom(
-                Status::InternalServerError,
+        .map_err(db_error)?;
+
+    // unknown emails still pay for a password hash so timing does not reveal which exist
+    let password_matches = match &record {
+        Some(record) => record.password.as_ref().is_some_and(|password_hash| {
+            verify_password(&user_credential.password, password_hash).is_ok()
+        }),
+        None => {
+            verify_dummy_password(&user_credential.password);
+            false
+        }
+    };
+    let record = match record {
+        Some(record) if password_matches => record,
+        _ => {
+            record_failed_sign_in(db_pool.inner(), &email, &ip)
+                .await
+                .map_err(db_error)?;
+            return Err(status::Custom(
+                Status::Unauthorized,
                 Json(ResponseError {
-                    error: "Database Error".to_string(),
+                    error: "Invalid email or password".to_string(),
                 }),
             )
-        })?;
+            .into());
+        }
+    };
 
     let created_at: DateTime<Utc> = timestamp_to_datetime!(record).expect("faild to parse date");
     let user = User {
         created_at: created_at,
         email: record.email,
         password: None,
+        email_verified_at: timestamp_to_datetime!(record, email_verified_at),
+        avatar: avatar_urls(record.id as i64, record.avatar_version.as_deref()),
         username: record.username,
         id: record.id,
         role: record.role,
     };
-    if verify_password(
-        &user_credential.password,
-        &record.password.expect("faild to parse password"),
-    )
-    .is_err()
-    {
-        return Err(status::Custom(
-            Status::Unauthorized,
-            Json(ResponseError {
-                error: "Invalid email or password".to_string(),
-            }),
-        ));
+    // with 2FA enabled the session only starts once /auth/2fa/verify accepts a code, the
+    // lockout is only reset there so a known password does not buy fresh code guesses
+    if record.totp_enabled_at.is_some() {
+        let challenge =
+            start_two_factor_challenge(db_pool.inner(), keyring, user.id as i64).await?;
+        return Ok(Json(SignInResponse::TwoFactorChallenge(challenge)));
     }
-    let token = generate_jwt(&record.id.to_string(), &user.role).map_err(|_| {
-        status::Custom(
-            Status::InternalServerError,
-            Json(ResponseError {
-                error: "faild to issue jwt token".to_string(),
-            }),
-        )
-    })?;
-    cookie.add(Cookie::build(("auth_token", token)).http_only(true));
-    Ok(Json(user))
+    clear_failed_sign_ins(db_pool.inner(), &email)
+        .await
+        .map_err(db_error)?;
+    start_session(db_pool, keyring, cookie, user.id as i64, &user.role).await?;
+    Ok(Json(SignInResponse::User(user)))
 }
 
 #[post("/sign-up", data = "<user_data>")]
 pub async fn sign_up(
     db_pool: &rocket::State<Db>,
+    keyring: &rocket::State<Keyring>,
+    mailer: &rocket::State<Mail>,
     cookie: &CookieJar<'_>,
     user_data: Json<NewUser>,
 ) -> Result<Json<User>, status::Custom<Json<ResponseError>>> {
+    let mut conn = db_pool.acquire().await.map_err(db_error)?;
+    let handle = match &user_data.handle {
+        Some(handle) => {
+            let handle = normalize_handle(handle);
+            validate_handle(&handle).map_err(|error| {
+                status::Custom(Status::BadRequest, Json(ResponseError { error }))
+            })?;
+            if !is_handle_available(&mut *conn, &handle, None)
+                .await
+                .map_err(db_error)?
+            {
+                return Err(status::Custom(
+                    Status::Conflict,
+                    Json(ResponseError {
+                        error: "Handle is already taken".to_string(),
+                    }),
+                ));
+            }
+            handle
+        }
+        None => generate_handle(&mut *conn, &user_data.username)
+            .await
+            .map_err(db_error)?,
+    };
+    drop(conn);
+
     let password_hash = hash_password(&user_data.password);
 
     let query = sqlx::query!(
-        "INSERT INTO users (username,email,password) VALUES (? ,? ,?)",
+        "INSERT INTO users (username,handle,email,password) VALUES (? ,? ,? ,?)",
         user_data.username,
+        handle,
         user_data.email,
         password_hash
     )
@@ -142,15 +299,228 @@ pub async fn sign_up(
         created_at: Utc::now(),
         role: record.role,
         password: None,
+        email_verified_at: None,
+        avatar: None,
     };
-    let token = generate_jwt(&record.id.to_string(), &user.role).map_err(|_| {
+    // a failed mail must not fail the sign up, the user can ask for a new link
+    let _ = send_verification_email(keyring, mailer, user.id as i64, &user.email).await;
+    start_session(db_pool, keyring, cookie, user.id as i64, &user.role).await?;
+    Ok(Json(user))
+}
+
+#[post("/refresh")]
+pub async fn refresh(
+    db_pool: &rocket::State<Db>,
+    keyring: &rocket::State<Keyring>,
+    cookie: &CookieJar<'_>,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let unauthorized = || {
         status::Custom(
-            Status::InternalServerError,
+            Status::Unauthorized,
             Json(ResponseError {
-                error: "faild to issue jwt token".to_string(),
+                error: "Invalid or expired refresh token".to_string(),
+            }),
+        )
+    };
+    let refresh_token = cookie
+        .get("refresh_token")
+        .map(|c| c.value().to_string())
+        .ok_or_else(unauthorized)?;
+
+    // rotate the refresh token so every token can only be used once
+    let (session, new_refresh_token) = rotate_session(db_pool.inner(), &refresh_token)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(unauthorized)?;
+
+    set_auth_cookies(
+        keyring,
+        cookie,
+        session.user_id,
+        &session.role,
+        session.id,
+        new_refresh_token,
+    )?;
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+#[post("/sign-out")]
+pub async fn sign_out(
+    db_pool: &rocket::State<Db>,
+    cookie: &CookieJar<'_>,
+    user: JwtAuth,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    revoke_session(db_pool.inner(), user.claims.sid, user_id)
+        .await
+        .map_err(db_error)?;
+    cookie.remove(Cookie::from("auth_token"));
+    cookie.remove(Cookie::build("refresh_token").path("/auth"));
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+#[post("/forgot-password", data = "<request>")]
+pub async fn forgot_password(
+    db_pool: &rocket::State<Db>,
+    mailer: &rocket::State<Mail>,
+    request: Json<ForgotPassword>,
+) -> Result<Json<String>, status::Custom<Json<ResponseError>>> {
+    let user = sqlx::query!("SELECT id FROM users WHERE email = ?", request.email)
+        .fetch_optional(db_pool.inner())
+        .await
+        .map_err(db_error)?;
+
+    // answer the same way whether or not the email is registered
+    if let Some(user) = user {
+        let token = generate_token();
+        sqlx::query!(
+            "INSERT INTO password_resets (user_id, token_hash, expires_at) VALUES (?, ?, NOW() + INTERVAL ? MINUTE)",
+            user.id,
+            hash_token(&token),
+            PASSWORD_RESET_TTL_MINUTES
+        )
+        .execute(db_pool.inner())
+        .await
+        .map_err(db_error)?;
+
+        let email = Email {
+            to: request.email.clone(),
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "Use the link below to choose a new password. It expires in {} minutes.\n\n{}/reset-password?token={}\n\nIf you did not ask for this, you can ignore this email.",
+                PASSWORD_RESET_TTL_MINUTES,
+                app_url(),
+                token
+            ),
+        };
+        mailer.send(&email).await.map_err(|_| {
+            status::Custom(
+                Status::InternalServerError,
+                Json(ResponseError {
+                    error: "Failed to send email".to_string(),
+                }),
+            )
+        })?;
+    }
+
+    Ok(Json(
+        "If an account exists for this email, a reset link has been sent.".to_string(),
+    ))
+}
+
+#[post("/reset-password", data = "<request>")]
+pub async fn reset_password(
+    db_pool: &rocket::State<Db>,
+    request: Json<ResetPassword>,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let mut tx = db_pool.begin().await.map_err(db_error)?;
+
+    let reset = sqlx::query!(
+        "SELECT user_id FROM password_resets WHERE token_hash = ? AND used_at IS NULL AND expires_at > NOW() FOR UPDATE",
+        hash_token(&request.token)
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| {
+        status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "Invalid or expired reset token".to_string(),
             }),
         )
     })?;
-    cookie.add(Cookie::build(("auth_token", token)).http_only(true));
-    Ok(Json(user))
+
+    sqlx::query!(
+        "UPDATE users SET password = ? WHERE id = ?",
+        hash_password(&request.password),
+        reset.user_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    // tokens are single use, any other outstanding token for the user is burned as well
+    sqlx::query!(
+        "UPDATE password_resets SET used_at = NOW() WHERE user_id = ? AND used_at IS NULL",
+        reset.user_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    // whoever knew the old password must not stay signed in
+    revoke_all_sessions(&mut *tx, reset.user_id as i64)
+        .await
+        .map_err(db_error)?;
+
+    tx.commit().await.map_err(db_error)?;
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+#[get("/verify-email?<token>")]
+pub async fn verify_email(
+    db_pool: &rocket::State<Db>,
+    keyring: &rocket::State<Keyring>,
+    token: &str,
+) -> Result<Json<String>, status::Custom<Json<ResponseError>>> {
+    let claims = verify_email_verification_token(keyring, token)
+        .map_err(|_| {
+            status::Custom(
+                Status::BadRequest,
+                Json(ResponseError {
+                    error: "Invalid or expired verification link".to_string(),
+                }),
+            )
+        })?
+        .claims;
+    let user_id = claims.sub.parse::<i64>().unwrap();
+
+    // the email must still match, so links sent to a previous address stop working
+    let result = sqlx::query!(
+        "UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()) WHERE id = ? AND email = ?",
+        user_id,
+        claims.email
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err(status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "Invalid or expired verification link".to_string(),
+            }),
+        ));
+    }
+
+    Ok(Json("Email verified.".to_string()))
+}
+
+#[post("/resend-verification")]
+pub async fn resend_verification(
+    db_pool: &rocket::State<Db>,
+    keyring: &rocket::State<Keyring>,
+    mailer: &rocket::State<Mail>,
+    user: JwtAuth,
+) -> Result<Json<String>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let record = sqlx::query!(
+        "SELECT email, email_verified_at FROM users WHERE id = ?",
+        user_id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    if record.email_verified_at.is_some() {
+        return Err(status::Custom(
+            Status::Conflict,
+            Json(ResponseError {
+                error: "Email is already verified".to_string(),
+            }),
+        ));
+    }
+
+    send_verification_email(keyring, mailer, user_id, &record.email).await?;
+    Ok(Json("Verification email sent.".to_string()))
 }
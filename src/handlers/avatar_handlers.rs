@@ -0,0 +1,148 @@
+use rocket::{form::Form, http::Status, response::status, serde::json::Json};
+use tokio::io::AsyncReadExt;
+
+use crate::{
+    auth::token::generate_token,
+    avatar::{avatar_dir, avatar_key, render_avatar, AVATAR_SIZES, MAX_AVATAR_BYTES},
+    db::Db,
+    guards::jwt_guard::JwtAuth,
+    handlers::user::load_profile,
+    models::{
+        error::{db_error, ResponseError},
+        user::{AvatarImage, AvatarUpload, Profile},
+    },
+    storage::Store,
+};
+
+fn storage_error() -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::InternalServerError,
+        Json(ResponseError {
+            error: "Failed to store avatar".to_string(),
+        }),
+    )
+}
+
+// multipart form with a single `avatar` file field
+#[put("/me/avatar", data = "<upload>")]
+pub async fn upload_avatar(
+    db_pool: &rocket::State<Db>,
+    storage: &rocket::State<Store>,
+    user: JwtAuth,
+    upload: Form<AvatarUpload<'_>>,
+) -> Result<Json<Profile>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    if upload.avatar.len() > MAX_AVATAR_BYTES {
+        return Err(status::Custom(
+            Status::PayloadTooLarge,
+            Json(ResponseError {
+                error: format!("Avatar must be at most {} bytes", MAX_AVATAR_BYTES),
+            }),
+        ));
+    }
+
+    let mut bytes = Vec::new();
+    upload
+        .avatar
+        .open()
+        .await
+        .map_err(|_| storage_error())?
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(|_| storage_error())?;
+
+    // decoding and resizing is cpu bound, keep it off the async workers
+    let thumbnails = tokio::task::spawn_blocking(move || render_avatar(&bytes))
+        .await
+        .map_err(|_| storage_error())?
+        .map_err(|e| {
+            status::Custom(
+                Status::UnprocessableEntity,
+                Json(ResponseError {
+                    error: e.message().to_string(),
+                }),
+            )
+        })?;
+
+    let version = generate_token()[..16].to_string();
+    for (size, thumbnail) in thumbnails {
+        storage
+            .put(&avatar_key(user_id, &version, size), thumbnail)
+            .await
+            .map_err(|_| storage_error())?;
+    }
+
+    let old_version = sqlx::query_scalar!("SELECT avatar_version FROM users WHERE id = ?", user_id)
+        .fetch_one(db_pool.inner())
+        .await
+        .map_err(db_error)?;
+    sqlx::query!(
+        "UPDATE users SET avatar_version = ? WHERE id = ?",
+        version,
+        user_id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    // the old files are no longer referenced, failing to remove them is harmless
+    if let Some(old_version) = old_version {
+        let _ = storage
+            .delete(&format!("{}/{}", avatar_dir(user_id), old_version))
+            .await;
+    }
+
+    Ok(Json(load_profile(db_pool, user_id).await?))
+}
+
+#[delete("/me/avatar")]
+pub async fn delete_avatar(
+    db_pool: &rocket::State<Db>,
+    storage: &rocket::State<Store>,
+    user: JwtAuth,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    sqlx::query!(
+        "UPDATE users SET avatar_version = NULL WHERE id = ?",
+        user_id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    storage
+        .delete(&avatar_dir(user_id))
+        .await
+        .map_err(|_| storage_error())?;
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+#[get("/avatars/<user_id>/<version>/<file>")]
+pub async fn get_avatar(
+    storage: &rocket::State<Store>,
+    user_id: i64,
+    version: &str,
+    file: &str,
+) -> Result<AvatarImage, status::Custom<Json<ResponseError>>> {
+    let not_found = || {
+        status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "Avatar not found".to_string(),
+            }),
+        )
+    };
+    // only serve the thumbnails we generate, never arbitrary keys
+    let size_known = AVATAR_SIZES
+        .iter()
+        .any(|size| file == format!("{}.webp", size));
+    if !size_known || !version.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(not_found());
+    }
+    let key = format!("{}/{}/{}", avatar_dir(user_id), version, file);
+    let bytes = storage
+        .get(&key)
+        .await
+        .map_err(|_| storage_error())?
+        .ok_or_else(not_found)?;
+    Ok(AvatarImage::new(bytes))
+}
@@ -0,0 +1,24 @@
+use rocket::{serde::json::Json, Request};
+
+use crate::models::error::{AuthError, AuthResponseError};
+
+// the auth guards store why they rejected the request
+fn auth_failure(request: &Request, fallback: AuthError) -> Json<AuthResponseError> {
+    let reason = request
+        .local_cache(|| None::<AuthError>)
+        .unwrap_or(fallback);
+    Json(AuthResponseError {
+        error: reason.message().to_string(),
+        reason,
+    })
+}
+
+#[catch(401)]
+pub fn unauthorized(request: &Request) -> Json<AuthResponseError> {
+    auth_failure(request, AuthError::Missing)
+}
+
+#[catch(403)]
+pub fn forbidden(request: &Request) -> Json<AuthResponseError> {
+    auth_failure(request, AuthError::InsufficientScope)
+}
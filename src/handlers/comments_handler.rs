@@ -1,6 +1,14 @@
 use crate::{
+    auth::permissions::{
+        require_ownership_permission, require_permission, COMMENT_CREATE, COMMENT_DELETE_OWN,
+        COMMENT_MODERATE, COMMENT_UPDATE_OWN,
+    },
     db::Db,
-    guards::jwt_guard::JwtAuth,
+    guards::{
+        jwt_guard::JwtAuth,
+        scope_guard::{CommentsWrite, ScopeAuth},
+        verified_guard::VerifiedAuth,
+    },
     models::{
         comment::{Comment, CommentBody},
         error::ResponseError,
@@ -10,28 +18,41 @@ use blog_api::timestamp_to_datetime;
 use chrono::{DateTime, Utc};
 use rocket::{http::Status, response::status, serde::json::Json};
 
+// comments on published posts, authors also see them on their own unpublished posts.
+// signed in callers do not see comments from users they blocked
 #[get("/comment/<post_id>")]
 pub async fn get_comment(
     db_pool: &rocket::State<Db>,
+    viewer: Option<JwtAuth>,
     post_id: i64,
 ) -> Result<Json<Vec<Comment>>, status::Custom<Json<ResponseError>>> {
-    let query = sqlx::query!("SELECT * FROM comments WHERE post_id = ?", post_id)
-        .fetch_all(db_pool.inner())
-        .await
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => status::Custom(
-                Status::NotFound,
-                Json(ResponseError {
-                    error: "Comment not found".to_string(),
-                }),
-            ),
-            _ => status::Custom(
-                Status::InternalServerError,
-                Json(ResponseError {
-                    error: "Database Error".to_string(),
-                }),
-            ),
-        })?;
+    let viewer_id = viewer.and_then(|viewer| viewer.claims.sub.parse::<i64>().ok());
+    let query = sqlx::query!(
+        "SELECT * FROM comments WHERE post_id = ? AND deleted_at IS NULL
+         AND post_id IN (SELECT id FROM posts WHERE deleted_at IS NULL
+            AND (status = 'published' OR author_id = ?))
+         AND (? IS NULL OR author_id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?))",
+        post_id,
+        viewer_id,
+        viewer_id,
+        viewer_id
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::RowNotFound => status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "Comment not found".to_string(),
+            }),
+        ),
+        _ => status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Database Error".to_string(),
+            }),
+        ),
+    })?;
 
     let comments: Vec<Comment> = query
         .iter()
@@ -53,29 +74,61 @@ pub async fn get_comment(
 #[post("/comment/<post_id>", data = "<comment_body>")]
 pub async fn create_comment(
     db_pool: &rocket::State<Db>,
-    user: JwtAuth,
+    user: VerifiedAuth<CommentsWrite>,
     post_id: i64,
     comment_body: Json<CommentBody>,
 ) -> Result<Json<Comment>, status::Custom<Json<ResponseError>>> {
-    sqlx::query_scalar!("SELECT EXISTS(SELECT 1 FROM posts WHERE id = ?)", post_id)
-        .fetch_one(db_pool.inner())
-        .await
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => status::Custom(
-                Status::NotFound,
-                Json(ResponseError {
-                    error: "Post not found".to_string(),
-                }),
-            ),
-            _ => status::Custom(
-                Status::InternalServerError,
-                Json(ResponseError {
-                    error: "Database Error".to_string(),
-                }),
-            ),
-        })?;
-
-    let author_id = user.claims.sub.parse::<i64>().unwrap();
+    require_permission(db_pool.inner(), user.user_id, COMMENT_CREATE).await?;
+
+    let post_author_id = sqlx::query_scalar!(
+        "SELECT author_id FROM posts WHERE id = ? AND status = 'published' AND deleted_at IS NULL",
+        post_id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Database Error".to_string(),
+            }),
+        )
+    })?
+    .ok_or_else(|| {
+        status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "Post not found".to_string(),
+            }),
+        )
+    })?;
+
+    // users blocked by the post author cannot comment on their posts
+    let blocked = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM blocks WHERE blocker_id = ? AND blocked_id = ?)",
+        post_author_id,
+        user.user_id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Database Error".to_string(),
+            }),
+        )
+    })?;
+    if blocked != 0 {
+        return Err(status::Custom(
+            Status::Forbidden,
+            Json(ResponseError {
+                error: "You cannot comment on this post".to_string(),
+            }),
+        ));
+    }
+
+    let author_id = user.user_id;
     // Insert the new comment if the post exists
     let result = sqlx::query!(
         "INSERT INTO comments (post_id, author_id, body) VALUES (?, ?, ?)",
@@ -106,24 +159,28 @@ pub async fn create_comment(
 #[put("/post/<post_id>/comment/<comment_id>", data = "<comment>")]
 pub async fn update_comment(
     db_pool: &rocket::State<Db>,
-    user: JwtAuth,
+    user: ScopeAuth<CommentsWrite>,
     post_id: i64,
     comment_id: i64,
     comment: Json<CommentBody>,
 ) -> Result<Json<String>, status::Custom<Json<ResponseError>>> {
+    require_permission(db_pool.inner(), user.user_id, COMMENT_UPDATE_OWN).await?;
+
     // Check if the post exists
-    let post_exists =
-        sqlx::query_scalar!("SELECT EXISTS(SELECT 1 FROM posts WHERE id = ?)", post_id)
-            .fetch_one(db_pool.inner())
-            .await
-            .map_err(|_| {
-                status::Custom(
-                    Status::InternalServerError,
-                    Json(ResponseError {
-                        error: "Database Error".to_string(),
-                    }),
-                )
-            })?;
+    let post_exists = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM posts WHERE id = ? AND deleted_at IS NULL)",
+        post_id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Database Error".to_string(),
+            }),
+        )
+    })?;
 
     // Convert the result to a boolean
     if post_exists == 0 {
@@ -135,12 +192,12 @@ pub async fn update_comment(
         ));
     }
 
-    // Extract author ID from JWT claims
-    let author_id = user.claims.sub.parse::<i64>().unwrap();
+    // Extract author ID from the session or api key
+    let author_id = user.user_id;
 
     // Update the comment for the given comment_id and author_id
     let result = sqlx::query!(
-        "UPDATE comments SET body = ? WHERE id = ? AND author_id = ?",
+        "UPDATE comments SET body = ? WHERE id = ? AND author_id = ? AND deleted_at IS NULL",
         comment.body,
         comment_id,
         author_id
@@ -172,14 +229,46 @@ pub async fn update_comment(
 #[delete("/comment/<comment_id>")]
 pub async fn delete_comment(
     db_pool: &rocket::State<Db>,
-    user: JwtAuth,
+    user: ScopeAuth<CommentsWrite>,
     comment_id: i64,
 ) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
-    let author_id = user.claims.sub.parse::<i64>().unwrap();
-    let result = sqlx::query!(
-        "DELETE FROM comments WHERE id = ? AND author_id = ? ",
-        comment_id,
-        author_id
+    let author_id = sqlx::query_scalar!(
+        "SELECT author_id FROM comments WHERE id = ? AND deleted_at IS NULL",
+        comment_id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Database Error".to_string(),
+            }),
+        )
+    })?
+    .ok_or_else(|| {
+        status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "Comment not found".to_string(),
+            }),
+        )
+    })?;
+
+    // Authors remove their own comments, moderators remove anyone's
+    require_ownership_permission(
+        db_pool.inner(),
+        user.user_id,
+        author_id as i64,
+        COMMENT_DELETE_OWN,
+        COMMENT_MODERATE,
+    )
+    .await?;
+
+    // kept in the trash until it is restored or purged
+    sqlx::query!(
+        "UPDATE comments SET deleted_at = NOW() WHERE id = ? AND deleted_at IS NULL",
+        comment_id
     )
     .execute(db_pool.inner())
     .await
@@ -191,16 +280,72 @@ pub async fn delete_comment(
             }),
         )
     })?;
-    if result.rows_affected() == 0 {
-        return Err(status::Custom(
+
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+// takes a comment back out of the trash, with the same permissions as deleting it
+#[post("/comment/<comment_id>/restore")]
+pub async fn restore_comment(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<CommentsWrite>,
+    comment_id: i64,
+) -> Result<Json<Comment>, status::Custom<Json<ResponseError>>> {
+    let row = sqlx::query!(
+        "SELECT * FROM comments WHERE id = ? AND deleted_at IS NOT NULL",
+        comment_id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Database Error".to_string(),
+            }),
+        )
+    })?
+    .ok_or_else(|| {
+        status::Custom(
             Status::NotFound,
             Json(ResponseError {
-                error: "Comment not found or you're not authorized to delete it.".to_string(),
+                error: "Comment not found in the trash".to_string(),
             }),
-        ));
-    }
+        )
+    })?;
 
-    Ok(status::Custom(Status::NoContent, ()))
+    require_ownership_permission(
+        db_pool.inner(),
+        user.user_id,
+        row.author_id as i64,
+        COMMENT_DELETE_OWN,
+        COMMENT_MODERATE,
+    )
+    .await?;
+
+    sqlx::query!(
+        "UPDATE comments SET deleted_at = NULL WHERE id = ?",
+        comment_id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Database Error".to_string(),
+            }),
+        )
+    })?;
+
+    // a comment on a trashed post stays hidden until the post is restored too
+    Ok(Json(Comment {
+        id: row.id as i64,
+        author_id: row.author_id as i64,
+        post_id: row.post_id as i64,
+        body: row.body.clone(),
+        created_at: timestamp_to_datetime!(row).expect("faild to parse datatime"),
+    }))
 }
 
 // struct Comment {
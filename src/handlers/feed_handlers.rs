@@ -0,0 +1,174 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rocket::{http::Status, response::status, serde::json::Json};
+
+use crate::{
+    db::Db,
+    guards::jwt_guard::JwtAuth,
+    models::{
+        error::{db_error, ResponseError},
+        post::{post_from_row, CursorPagination, Post},
+        CursorResponse,
+    },
+    taxonomy::attach_tags,
+};
+
+const DEFAULT_FEED_LIMIT: usize = 20;
+const MAX_FEED_LIMIT: usize = 100;
+
+// the cursor is the position of the last post served: its publication time and id
+fn encode_cursor(published_at: i64, id: i32) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}:{}", published_at, id))
+}
+
+fn decode_cursor(cursor: &str) -> Option<(i64, i32)> {
+    let decoded = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+    let (published_at, id) = decoded.split_once(':')?;
+    Some((published_at.parse().ok()?, id.parse().ok()?))
+}
+
+#[post("/<id>/follow")]
+pub async fn follow_user(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    id: i64,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    if user_id == id {
+        return Err(status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "You cannot follow yourself".to_string(),
+            }),
+        ));
+    }
+    let exists = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE id = ? AND deleted_at IS NULL)",
+        id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    if exists == 0 {
+        return Err(status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "User not found".to_string(),
+            }),
+        ));
+    }
+
+    // a block in either direction removed the follows and keeps them from coming back
+    let blocked = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM blocks
+         WHERE (blocker_id = ? AND blocked_id = ?) OR (blocker_id = ? AND blocked_id = ?))",
+        user_id,
+        id,
+        id,
+        user_id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    if blocked != 0 {
+        return Err(status::Custom(
+            Status::Forbidden,
+            Json(ResponseError {
+                error: "You cannot follow this user".to_string(),
+            }),
+        ));
+    }
+
+    // following twice is not an error
+    sqlx::query!(
+        "INSERT IGNORE INTO follows (follower_id, followee_id) VALUES (?, ?)",
+        user_id,
+        id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+#[delete("/<id>/follow")]
+pub async fn unfollow_user(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    id: i64,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    sqlx::query!(
+        "DELETE FROM follows WHERE follower_id = ? AND followee_id = ?",
+        user_id,
+        id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+// newest first posts by the authors the user follows and has not muted
+#[get("/feed?<pagination..>")]
+pub async fn get_feed(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    pagination: CursorPagination,
+) -> Result<Json<CursorResponse<Post>>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let limit = pagination
+        .limit
+        .unwrap_or(DEFAULT_FEED_LIMIT)
+        .clamp(1, MAX_FEED_LIMIT) as i64;
+    let cursor = match &pagination.cursor {
+        Some(cursor) => Some(decode_cursor(cursor).ok_or_else(|| {
+            status::Custom(
+                Status::BadRequest,
+                Json(ResponseError {
+                    error: "Invalid cursor".to_string(),
+                }),
+            )
+        })?),
+        None => None,
+    };
+    let (before_published_at, before_id) = cursor.unzip();
+
+    // fetch one extra row to know whether there is another page
+    let query = sqlx::query!(
+        "SELECT p.* FROM posts p JOIN follows f ON f.followee_id = p.author_id
+         WHERE f.follower_id = ? AND p.status = 'published' AND p.deleted_at IS NULL
+         AND p.author_id NOT IN (SELECT muted_id FROM mutes WHERE muter_id = ?)
+         AND (? IS NULL OR p.published_at < FROM_UNIXTIME(?) OR (p.published_at = FROM_UNIXTIME(?) AND p.id < ?))
+         ORDER BY p.published_at DESC, p.id DESC LIMIT ?",
+        user_id,
+        user_id,
+        before_published_at,
+        before_published_at,
+        before_published_at,
+        before_id,
+        limit + 1
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let mut posts: Vec<Post> = query.iter().map(|row| post_from_row!(row)).collect();
+    attach_tags(db_pool.inner(), &mut posts)
+        .await
+        .map_err(db_error)?;
+
+    let next_cursor = if posts.len() as i64 > limit {
+        posts.truncate(limit as usize);
+        posts.last().map(|post| {
+            let published_at = post.published_at.unwrap_or(post.created_at);
+            encode_cursor(published_at.timestamp(), post.id)
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(CursorResponse {
+        data: posts,
+        next_cursor,
+    }))
+}
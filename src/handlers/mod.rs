@@ -1,4 +1,19 @@
+pub mod account_handlers;
+pub mod admin_handlers;
+pub mod api_key_handlers;
 pub mod auth_handlers;
+pub mod avatar_handlers;
+pub mod catchers;
 pub mod comments_handler;
+pub mod feed_handlers;
+pub mod oidc_handlers;
 pub mod post_handlers;
+pub mod relation_handlers;
+pub mod revision_handlers;
+pub mod search_handlers;
+pub mod taxonomy_handlers;
+pub mod trash_handlers;
+pub mod two_factor_handlers;
 pub mod user;
+pub mod vanity_handlers;
+pub mod well_known_handlers;
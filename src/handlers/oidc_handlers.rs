@@ -0,0 +1,369 @@
+use blog_api::timestamp_to_datetime;
+use chrono::{DateTime, Utc};
+use rocket::{
+    http::{Cookie, CookieJar, Status},
+    response::{status, Redirect},
+    serde::json::Json,
+};
+
+use blog_api::oidc::{generate_pkce, IdTokenClaims, Oidc, OidcProvider};
+
+use crate::{
+    auth::{keys::Keyring, password::hash_password, token::generate_token},
+    avatar::avatar_urls,
+    config::app_url,
+    db::Db,
+    guards::jwt_guard::JwtAuth,
+    handle::generate_handle,
+    handlers::auth_handlers::{start_session, start_two_factor_challenge},
+    models::{
+        error::{db_error, ResponseError},
+        user::{SignInResponse, User},
+    },
+};
+
+const OIDC_STATE_TTL_MINUTES: i64 = 10;
+
+fn provider_error<E>(_: E) -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::BadGateway,
+        Json(ResponseError {
+            error: "Identity provider request failed".to_string(),
+        }),
+    )
+}
+
+fn find_provider<'a>(
+    oidc: &'a Oidc,
+    provider: &str,
+) -> Result<&'a OidcProvider, status::Custom<Json<ResponseError>>> {
+    oidc.providers.get(provider).ok_or_else(|| {
+        status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "Unknown identity provider".to_string(),
+            }),
+        )
+    })
+}
+
+fn redirect_uri(provider: &str) -> String {
+    format!("{}/auth/oidc/{}/callback", app_url(), provider)
+}
+
+#[get("/oidc/<provider>/login")]
+pub async fn oidc_login(
+    db_pool: &rocket::State<Db>,
+    oidc: &rocket::State<Oidc>,
+    cookie: &CookieJar<'_>,
+    provider: &str,
+) -> Result<Redirect, status::Custom<Json<ResponseError>>> {
+    begin_login(db_pool, oidc, cookie, provider, None).await
+}
+
+// links the provider identity to the signed in account, the only way an identity
+// ever gets attached to an account that already exists
+#[get("/oidc/<provider>/link")]
+pub async fn oidc_link(
+    db_pool: &rocket::State<Db>,
+    oidc: &rocket::State<Oidc>,
+    cookie: &CookieJar<'_>,
+    user: JwtAuth,
+    provider: &str,
+) -> Result<Redirect, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    begin_login(db_pool, oidc, cookie, provider, Some(user_id)).await
+}
+
+// sends the browser to the provider, the callback finishes a sign in or, with
+// `link_user_id`, a link to that account
+async fn begin_login(
+    db_pool: &rocket::State<Db>,
+    oidc: &rocket::State<Oidc>,
+    cookie: &CookieJar<'_>,
+    provider: &str,
+    link_user_id: Option<i64>,
+) -> Result<Redirect, status::Custom<Json<ResponseError>>> {
+    let provider_config = find_provider(oidc, provider)?;
+    let metadata = oidc
+        .discover(provider_config)
+        .await
+        .map_err(provider_error)?;
+
+    let state = generate_token();
+    let nonce = generate_token();
+    let pkce = generate_pkce();
+    sqlx::query!(
+        "INSERT INTO oidc_states (state, provider, nonce, code_verifier, link_user_id, expires_at)
+         VALUES (?, ?, ?, ?, ?, NOW() + INTERVAL ? MINUTE)",
+        state,
+        provider,
+        nonce,
+        pkce.verifier,
+        link_user_id,
+        OIDC_STATE_TTL_MINUTES
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let url = oidc
+        .authorization_url(
+            &metadata,
+            provider_config,
+            &redirect_uri(provider),
+            &state,
+            &nonce,
+            &pkce.challenge,
+        )
+        .map_err(provider_error)?;
+
+    // ties the callback to the browser that started the login
+    cookie.add(
+        Cookie::build(("oidc_state", state))
+            .path("/auth/oidc")
+            .http_only(true),
+    );
+    Ok(Redirect::to(url))
+}
+
+#[get("/oidc/<provider>/callback?<code>&<state>&<error>")]
+pub async fn oidc_callback(
+    db_pool: &rocket::State<Db>,
+    keyring: &rocket::State<Keyring>,
+    oidc: &rocket::State<Oidc>,
+    cookie: &CookieJar<'_>,
+    provider: &str,
+    code: Option<&str>,
+    state: &str,
+    error: Option<&str>,
+) -> Result<Json<SignInResponse>, status::Custom<Json<ResponseError>>> {
+    let provider_config = find_provider(oidc, provider)?;
+    let invalid_state = || {
+        status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "Invalid or expired login attempt".to_string(),
+            }),
+        )
+    };
+
+    if cookie.get("oidc_state").map(|c| c.value()) != Some(state) {
+        return Err(invalid_state());
+    }
+    cookie.remove(Cookie::build("oidc_state").path("/auth/oidc"));
+
+    let login = sqlx::query!(
+        "SELECT nonce, code_verifier, link_user_id FROM oidc_states
+         WHERE state = ? AND provider = ? AND expires_at > NOW()",
+        state,
+        provider
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(invalid_state)?;
+    // a state can only be used once
+    let deleted = sqlx::query!("DELETE FROM oidc_states WHERE state = ?", state)
+        .execute(db_pool.inner())
+        .await
+        .map_err(db_error)?;
+    if deleted.rows_affected() == 0 {
+        return Err(invalid_state());
+    }
+
+    let code = match (code, error) {
+        (Some(code), None) => code,
+        _ => {
+            return Err(status::Custom(
+                Status::Unauthorized,
+                Json(ResponseError {
+                    error: "Sign in was cancelled at the identity provider".to_string(),
+                }),
+            ))
+        }
+    };
+
+    let metadata = oidc
+        .discover(provider_config)
+        .await
+        .map_err(provider_error)?;
+    let id_token = oidc
+        .exchange_code(
+            &metadata,
+            provider_config,
+            code,
+            &redirect_uri(provider),
+            &login.code_verifier,
+        )
+        .await
+        .map_err(provider_error)?;
+    let claims = oidc
+        .validate_id_token(&metadata, provider_config, &id_token, &login.nonce)
+        .await
+        .map_err(|_| {
+            status::Custom(
+                Status::Unauthorized,
+                Json(ResponseError {
+                    error: "Invalid id token".to_string(),
+                }),
+            )
+        })?;
+
+    let user_id = match login.link_user_id {
+        Some(user_id) => {
+            link_identity(db_pool, provider, &claims, user_id as i64).await?;
+            user_id as i64
+        }
+        None => find_or_create_user(db_pool, provider, &claims).await?,
+    };
+    let record = sqlx::query!("SELECT * FROM users WHERE id = ?", user_id)
+        .fetch_one(db_pool.inner())
+        .await
+        .map_err(db_error)?;
+    let user = User {
+        created_at: timestamp_to_datetime!(record).expect("faild to parse date"),
+        email: record.email,
+        password: None,
+        email_verified_at: timestamp_to_datetime!(record, email_verified_at),
+        avatar: avatar_urls(record.id as i64, record.avatar_version.as_deref()),
+        username: record.username,
+        id: record.id,
+        role: record.role,
+    };
+    // linking happens inside a session that already exists
+    if login.link_user_id.is_some() {
+        return Ok(Json(SignInResponse::User(user)));
+    }
+    // the provider stands in for the password, not for the second factor
+    if record.totp_enabled_at.is_some() {
+        let challenge = start_two_factor_challenge(db_pool.inner(), keyring, user_id).await?;
+        return Ok(Json(SignInResponse::TwoFactorChallenge(challenge)));
+    }
+    start_session(db_pool, keyring, cookie, user_id, &user.role).await?;
+    Ok(Json(SignInResponse::User(user)))
+}
+
+// attaches the identity to `user_id` unless another account already has it
+async fn link_identity(
+    db_pool: &rocket::State<Db>,
+    provider: &str,
+    claims: &IdTokenClaims,
+    user_id: i64,
+) -> Result<(), status::Custom<Json<ResponseError>>> {
+    let linked = sqlx::query_scalar!(
+        "SELECT user_id FROM user_identities WHERE provider = ? AND subject = ?",
+        provider,
+        claims.sub
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    match linked {
+        Some(linked_id) if linked_id as i64 == user_id => Ok(()),
+        Some(_) => Err(status::Custom(
+            Status::Conflict,
+            Json(ResponseError {
+                error: "This identity is already linked to another account".to_string(),
+            }),
+        )),
+        None => {
+            sqlx::query!(
+                "INSERT INTO user_identities (user_id, provider, subject, email) VALUES (?, ?, ?, ?)",
+                user_id,
+                provider,
+                claims.sub,
+                claims.email
+            )
+            .execute(db_pool.inner())
+            .await
+            .map_err(db_error)?;
+            Ok(())
+        }
+    }
+}
+
+// resolve the provider identity to a user, creating one on first sign in
+async fn find_or_create_user(
+    db_pool: &rocket::State<Db>,
+    provider: &str,
+    claims: &IdTokenClaims,
+) -> Result<i64, status::Custom<Json<ResponseError>>> {
+    let linked = sqlx::query_scalar!(
+        "SELECT user_id FROM user_identities WHERE provider = ? AND subject = ?",
+        provider,
+        claims.sub
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    if let Some(user_id) = linked {
+        return Ok(user_id as i64);
+    }
+
+    let email = claims.email.clone().ok_or_else(|| {
+        status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "Identity provider did not share an email address".to_string(),
+            }),
+        )
+    })?;
+    let email_verified = claims.email_verified.unwrap_or(false);
+
+    let mut tx = db_pool.begin().await.map_err(db_error)?;
+    let existing = sqlx::query_scalar!("SELECT id FROM users WHERE email = ?", email)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    let user_id = match existing {
+        // whoever controls the address at some provider does not own the account, the
+        // owner signs in as usual and links the provider from there
+        Some(_) => {
+            return Err(status::Custom(
+                Status::Conflict,
+                Json(ResponseError {
+                    error: "An account with this email already exists, sign in and link this provider to it".to_string(),
+                }),
+            ))
+        }
+        None => {
+            let username = claims
+                .preferred_username
+                .clone()
+                .or_else(|| claims.name.clone())
+                .unwrap_or_else(|| email.split('@').next().unwrap_or("user").to_string());
+            let handle = generate_handle(&mut *tx, &username)
+                .await
+                .map_err(db_error)?;
+            // random password, the account can set a real one through forgot-password
+            let password_hash = hash_password(&generate_token());
+            let result = sqlx::query!(
+                "INSERT INTO users (username, handle, email, password, email_verified_at) VALUES (?, ?, ?, ?, IF(?, NOW(), NULL))",
+                username,
+                handle,
+                email,
+                password_hash,
+                email_verified
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+            result.last_insert_id() as i64
+        }
+    };
+
+    sqlx::query!(
+        "INSERT INTO user_identities (user_id, provider, subject, email) VALUES (?, ?, ?, ?)",
+        user_id,
+        provider,
+        claims.sub,
+        email
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(user_id)
+}
@@ -1,47 +1,129 @@
-use blog_api::timestamp_to_datetime;
 use chrono::{DateTime, Utc};
-use rocket::{http::Status, response::status, serde::json::Json};
+use rocket::{
+    http::Status,
+    response::{status, Redirect},
+    serde::json::Json,
+};
+use sqlx::{MySql, QueryBuilder};
 
 use crate::{
+    auth::permissions::{
+        has_permission, require_ownership_permission, require_permission, POST_CREATE,
+        POST_DELETE_ANY, POST_DELETE_OWN, POST_UPDATE_ANY, POST_UPDATE_OWN,
+    },
     db::Db,
-    guards::jwt_guard::JwtAuth,
+    guards::{
+        jwt_guard::JwtAuth,
+        scope_guard::{PostsRead, PostsWrite, ScopeAuth},
+        verified_guard::VerifiedAuth,
+    },
+    handle::HandleResponse,
+    markdown::render_markdown,
     models::{
-        error::ResponseError,
-        post::{NewPost, Pagination, Post, UpdatedPost},
+        error::{db_error, ResponseError},
+        post::{post_from_row, NewPost, Pagination, Post, PostStatus, SchedulePost, UpdatedPost},
         PagedResponse,
     },
+    revisions::{record_revision, revise_post},
+    slug::{generate_slug, resolve_slug, SlugLookup},
+    taxonomy::{attach_tags, category_exists, normalize_tag, normalize_tags, set_post_tags},
 };
 
+fn bad_request(error: &str) -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::BadRequest,
+        Json(ResponseError {
+            error: error.to_string(),
+        }),
+    )
+}
+
+async fn check_category(
+    db_pool: &rocket::State<Db>,
+    category_id: Option<i64>,
+) -> Result<(), status::Custom<Json<ResponseError>>> {
+    if let Some(category_id) = category_id {
+        if !category_exists(db_pool.inner(), category_id)
+            .await
+            .map_err(db_error)?
+        {
+            return Err(bad_request("Unknown category"));
+        }
+    }
+    Ok(())
+}
+
 // create post
 #[post("/post", data = "<new_post>")]
 pub async fn create_post(
     db_pool: &rocket::State<Db>,
-    user: JwtAuth,
+    user: VerifiedAuth<PostsWrite>,
     new_post: Json<NewPost>,
 ) -> Result<Json<Post>, status::Custom<Json<ResponseError>>> {
+    require_permission(db_pool.inner(), user.user_id, POST_CREATE).await?;
+
+    let status = new_post.status.unwrap_or(PostStatus::Draft);
+    if status == PostStatus::Scheduled {
+        return Err(status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "Schedule the post once it is created".to_string(),
+            }),
+        ));
+    }
+    let tags = normalize_tags(new_post.tags.as_deref().unwrap_or_default())
+        .map_err(|e| bad_request(&e))?;
+    check_category(db_pool, new_post.category_id).await?;
+
+    let published_at = (status == PostStatus::Published).then(Utc::now);
+    let body_html = render_markdown(&new_post.body);
+    let mut tx = db_pool.begin().await.map_err(db_error)?;
+    let slug = generate_slug(&mut tx, &new_post.title, None)
+        .await
+        .map_err(db_error)?;
     let query = sqlx::query!(
-        "INSERT INTO posts (author_id, title, body) VALUES (?, ? ,?)",
-        user.claims.sub.parse::<i64>().unwrap(),
+        "INSERT INTO posts (author_id, title, slug, body, body_html, status, published_at, category_id)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        user.user_id,
         new_post.title,
-        new_post.body
+        slug,
+        new_post.body,
+        body_html,
+        status.as_str(),
+        published_at,
+        new_post.category_id
     )
-    .execute(db_pool.inner())
+    .execute(&mut *tx)
     .await
-    .map_err(|_| {
-        status::Custom(
-            Status::InternalServerError,
-            Json(ResponseError {
-                error: "Database Error".to_string(),
-            }),
-        )
-    })?;
+    .map_err(db_error)?;
+    let post_id = query.last_insert_id() as i64;
+    record_revision(
+        &mut tx,
+        post_id,
+        user.user_id,
+        &new_post.title,
+        &new_post.body,
+    )
+    .await
+    .map_err(db_error)?;
+    set_post_tags(&mut tx, post_id, &tags)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
 
     let post = Post {
-        id: query.last_insert_id() as i32,
-        author_id: user.claims.sub.parse().expect("fiald to parse author id"),
+        id: post_id as i32,
+        author_id: user.user_id as i32,
         body: new_post.body.clone(),
+        body_html,
         title: new_post.title.clone(),
+        slug,
+        status,
         created_at: Utc::now(),
+        published_at,
+        publish_at: None,
+        category_id: new_post.category_id.map(|category_id| category_id as i32),
+        tags,
     };
     Ok(Json(post))
 }
@@ -50,25 +132,15 @@ pub async fn create_post(
 #[get("/post/<id>")]
 pub async fn get_post(
     db_pool: &rocket::State<Db>,
-    user: JwtAuth,
+    user: ScopeAuth<PostsRead>,
     id: i64,
 ) -> Result<Json<Post>, status::Custom<Json<ResponseError>>> {
-    // Step 1: Parse the author's ID safely from JWT claims
-    let author_id = match user.claims.sub.parse::<i64>() {
-        Ok(id) => id,
-        Err(_) => {
-            return Err(status::Custom(
-                Status::BadRequest,
-                Json(ResponseError {
-                    error: "Invalid User ID".to_string(),
-                }),
-            ))
-        }
-    };
+    // Step 1: The author's ID comes from the session or api key
+    let author_id = user.user_id;
 
     // Step 2: Query the database to check if the post exists
     let record = sqlx::query!(
-        "SELECT * FROM posts WHERE id = ? AND author_id = ?",
+        "SELECT * FROM posts WHERE id = ? AND author_id = ? AND deleted_at IS NULL",
         id,
         author_id
     )
@@ -89,15 +161,12 @@ pub async fn get_post(
         ),
     })?;
 
-    // Step 3: Convert timestamp to DateTime and construct the Post object
-    let created_at = timestamp_to_datetime!(record).unwrap();
-    let post = Post {
-        id: record.id as i32,
-        author_id: record.author_id,
-        body: record.body,
-        title: record.title,
-        created_at,
-    };
+    // Step 3: Construct the Post object
+    let mut post = post_from_row!(record);
+
+    attach_tags(db_pool.inner(), std::slice::from_mut(&mut post))
+        .await
+        .map_err(db_error)?;
 
     // Step 4: Return the post as a JSON response
     Ok(Json(post))
@@ -107,15 +176,14 @@ pub async fn get_post(
 #[put("/post/<id>", data = "<post_data>")]
 pub async fn update_post(
     db_pool: &rocket::State<Db>,
-    user: JwtAuth,
+    user: ScopeAuth<PostsWrite>,
     id: i64,
     post_data: Json<UpdatedPost>, // Post data may contain None for optional fields
 ) -> Result<Json<Post>, status::Custom<Json<ResponseError>>> {
-    // Fetch the post to ensure it exists and belongs to the current user
+    // Fetch the post to ensure it exists
     let record = sqlx::query!(
-        "SELECT * FROM posts WHERE id = ? AND author_id = ?",
-        id,
-        user.claims.sub.parse::<i64>().unwrap()
+        "SELECT * FROM posts WHERE id = ? AND deleted_at IS NULL",
+        id
     )
     .fetch_one(db_pool.inner())
     .await
@@ -123,14 +191,25 @@ pub async fn update_post(
         status::Custom(
             Status::NotFound,
             Json(ResponseError {
-                error: "Post not found or you're not authorized to update it.".to_string(),
+                error: "Post not found".to_string(),
             }),
         )
     })?;
 
+    // Authors may edit their own posts, editors anyone's
+    require_ownership_permission(
+        db_pool.inner(),
+        user.user_id,
+        record.author_id as i64,
+        POST_UPDATE_OWN,
+        POST_UPDATE_ANY,
+    )
+    .await?;
+
     // Prepare dynamic update query depending on which fields are present
-    let mut title = record.title;
-    let mut body = record.body;
+    let record_title = record.title;
+    let mut title = record_title.clone();
+    let mut body = record.body.clone();
 
     // Update only if the new values are provided
     if let Some(ref new_title) = post_data.title {
@@ -139,34 +218,66 @@ pub async fn update_post(
     if let Some(ref new_body) = post_data.body {
         body = new_body.clone();
     }
+    let tags = match &post_data.tags {
+        Some(tags) => Some(normalize_tags(tags).map_err(|e| bad_request(&e))?),
+        None => None,
+    };
+    let category_id = match post_data.category_id {
+        Some(category_id) => {
+            check_category(db_pool, category_id).await?;
+            category_id.map(|category_id| category_id as i32)
+        }
+        None => record.category_id,
+    };
 
-    // Perform the update query
-    sqlx::query!(
-        "UPDATE posts SET title = ?, body = ? WHERE id = ? AND author_id = ?",
-        title,
-        body,
-        id,
-        user.claims.sub.parse::<i64>().unwrap()
-    )
-    .execute(db_pool.inner())
-    .await
-    .map_err(|_| {
+    let update_failed = |_: sqlx::Error| {
         status::Custom(
             Status::InternalServerError,
             Json(ResponseError {
                 error: "Failed to update post".to_string(),
             }),
         )
-    })?;
-    let created_at = timestamp_to_datetime!(record).unwrap();
+    };
+    let mut tx = db_pool.begin().await.map_err(update_failed)?;
+
+    // only a real change to the title or body becomes a new revision
+    let (slug, body_html) = if title != record_title || body != record.body {
+        revise_post(&mut tx, id, user.user_id, &title, &body)
+            .await
+            .map_err(update_failed)?
+    } else {
+        let body_html = record
+            .body_html
+            .clone()
+            .unwrap_or_else(|| render_markdown(&body));
+        (record.slug.clone(), body_html)
+    };
+    sqlx::query!(
+        "UPDATE posts SET category_id = ? WHERE id = ?",
+        category_id,
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(update_failed)?;
+    if let Some(tags) = &tags {
+        set_post_tags(&mut tx, id, tags)
+            .await
+            .map_err(update_failed)?;
+    }
+    tx.commit().await.map_err(update_failed)?;
     // Return the updated post
-    let updated_post = Post {
-        id: record.id as i32,
-        author_id: record.author_id,
+    let mut updated_post = Post {
         title,
+        slug,
         body,
-        created_at,
+        body_html,
+        category_id,
+        ..post_from_row!(record)
     };
+    attach_tags(db_pool.inner(), std::slice::from_mut(&mut updated_post))
+        .await
+        .map_err(db_error)?;
 
     Ok(Json(updated_post))
 }
@@ -174,21 +285,24 @@ pub async fn update_post(
 #[delete("/post/<id>")]
 pub async fn delete_post(
     db_pool: &rocket::State<Db>,
-    user: JwtAuth,
+    user: ScopeAuth<PostsWrite>,
     id: i64,
 ) -> Result<Json<String>, status::Custom<Json<ResponseError>>> {
     // First, let's check if the user is authorized to delete the post
-    let post_owner_id = sqlx::query_scalar!("SELECT author_id FROM posts WHERE id = ?", id)
-        .fetch_optional(&**db_pool)
-        .await
-        .map_err(|_| {
-            status::Custom(
-                Status::InternalServerError,
-                Json(ResponseError {
-                    error: "Error checking post owner".to_string(),
-                }),
-            )
-        })?;
+    let post_owner_id = sqlx::query_scalar!(
+        "SELECT author_id FROM posts WHERE id = ? AND deleted_at IS NULL",
+        id
+    )
+    .fetch_optional(&**db_pool)
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Error checking post owner".to_string(),
+            }),
+        )
+    })?;
 
     // If the post doesn't exist, return a 404 error
     if post_owner_id.is_none() {
@@ -202,47 +316,98 @@ pub async fn delete_post(
 
     let post_owner_id = post_owner_id.unwrap();
 
-    // Check if the user is either an admin or the owner of the post
-    if user
-        .claims
-        .sub
-        .parse::<i32>()
-        .expect("faild to parse user id")
-        != post_owner_id
-        && user.claims.role == "admin"
-    {
-        return Err(status::Custom(
-            Status::Forbidden,
+    // Owners need post.delete.own, everyone else post.delete.any
+    require_ownership_permission(
+        db_pool.inner(),
+        user.user_id,
+        post_owner_id as i64,
+        POST_DELETE_OWN,
+        POST_DELETE_ANY,
+    )
+    .await?;
+
+    // The post goes to the trash, its comments stay hidden with it until it is restored or purged
+    sqlx::query!(
+        "UPDATE posts SET deleted_at = NOW() WHERE id = ? AND deleted_at IS NULL",
+        id
+    )
+    .execute(&**db_pool)
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
             Json(ResponseError {
-                error: "You do not have permission to delete this post".to_string(),
+                error: "Error deleting the post".to_string(),
             }),
-        ));
-    }
-
-    // Proceed with deleting the post
-    sqlx::query!("DELETE FROM posts WHERE id = ?", id)
-        .execute(&**db_pool)
-        .await
-        .map_err(|_| {
-            status::Custom(
-                Status::InternalServerError,
-                Json(ResponseError {
-                    error: "Error deleting the post".to_string(),
-                }),
-            )
-        })?;
+        )
+    })?;
 
     // Return success message
-    Ok(Json("Post successfully deleted".to_string()))
+    Ok(Json("Post moved to the trash".to_string()))
 }
 
-// get all posts with paganation
+// takes a post back out of the trash, with the same permissions as deleting it
+#[post("/post/<id>/restore")]
+pub async fn restore_post(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsWrite>,
+    id: i64,
+) -> Result<Json<Post>, status::Custom<Json<ResponseError>>> {
+    let author_id = sqlx::query_scalar!(
+        "SELECT author_id FROM posts WHERE id = ? AND deleted_at IS NOT NULL",
+        id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| {
+        status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "Post not found in the trash".to_string(),
+            }),
+        )
+    })?;
+
+    require_ownership_permission(
+        db_pool.inner(),
+        user.user_id,
+        author_id as i64,
+        POST_DELETE_OWN,
+        POST_DELETE_ANY,
+    )
+    .await?;
+
+    sqlx::query!("UPDATE posts SET deleted_at = NULL WHERE id = ?", id)
+        .execute(db_pool.inner())
+        .await
+        .map_err(db_error)?;
+
+    let record = sqlx::query!("SELECT * FROM posts WHERE id = ?", id)
+        .fetch_one(db_pool.inner())
+        .await
+        .map_err(db_error)?;
+    let mut post = post_from_row!(record);
+    attach_tags(db_pool.inner(), std::slice::from_mut(&mut post))
+        .await
+        .map_err(db_error)?;
+    Ok(Json(post))
+}
 
-#[get("/post?<pagination..>")]
+// get all published posts with paganation, optionally with a tag or in a category
+// (subcategories included)
+#[get("/post?<tag>&<category>&<pagination..>")]
 pub async fn get_posts(
     db_pool: &rocket::State<Db>,
+    viewer: Option<JwtAuth>,
+    tag: Option<String>,
+    category: Option<String>,
     pagination: Option<Pagination>,
 ) -> Result<Json<PagedResponse<Post>>, status::Custom<Json<ResponseError>>> {
+    let tag = tag.as_deref().map(normalize_tag);
+    let category = category.map(|category| category.to_lowercase());
+    // signed in callers do not see posts from users they blocked or muted
+    let viewer_id = viewer.and_then(|viewer| viewer.claims.sub.parse::<i64>().ok());
     let page = pagination.as_ref().map_or(1, |p| p.page.unwrap_or(1)) as i64;
     let size = pagination.as_ref().map_or(10, |p| p.size.unwrap_or(10)) as i64;
 
@@ -251,30 +416,71 @@ pub async fn get_posts(
     let offset = (page - 1) * size;
 
     // Fetch the paginated posts
-    let query = sqlx::query!("SELECT * FROM posts LIMIT ? OFFSET ?", size, offset)
-        .fetch_all(db_pool.inner())
-        .await
-        .map_err(|_| {
-            status::Custom(
-                Status::InternalServerError,
-                Json(ResponseError {
-                    error: "Error while fetching data from the database".to_string(),
-                }),
-            )
-        })?;
+    let query = sqlx::query!(
+        "SELECT * FROM posts WHERE status = 'published' AND deleted_at IS NULL
+         AND (? IS NULL OR author_id NOT IN (
+            SELECT blocked_id FROM blocks WHERE blocker_id = ?
+            UNION SELECT muted_id FROM mutes WHERE muter_id = ?))
+         AND (? IS NULL OR id IN (
+            SELECT pt.post_id FROM post_tags pt JOIN tags t ON t.id = pt.tag_id WHERE t.name = ?))
+         AND (? IS NULL OR category_id IN (
+            WITH RECURSIVE tree AS (
+                SELECT id FROM categories WHERE slug = ?
+                UNION ALL SELECT c.id FROM categories c JOIN tree ON c.parent_id = tree.id)
+            SELECT id FROM tree))
+         ORDER BY published_at DESC LIMIT ? OFFSET ?",
+        viewer_id,
+        viewer_id,
+        viewer_id,
+        tag,
+        tag,
+        category,
+        category,
+        size,
+        offset
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Error while fetching data from the database".to_string(),
+            }),
+        )
+    })?;
 
     // Count the total number of posts
-    let total_items = sqlx::query_scalar!("SELECT COUNT(*) FROM posts")
-        .fetch_one(db_pool.inner())
-        .await
-        .map_err(|_| {
-            status::Custom(
-                Status::InternalServerError,
-                Json(ResponseError {
-                    error: "Error while fetching the count from the database".to_string(),
-                }),
-            )
-        })?;
+    let total_items = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM posts WHERE status = 'published' AND deleted_at IS NULL
+         AND (? IS NULL OR author_id NOT IN (
+            SELECT blocked_id FROM blocks WHERE blocker_id = ?
+            UNION SELECT muted_id FROM mutes WHERE muter_id = ?))
+         AND (? IS NULL OR id IN (
+            SELECT pt.post_id FROM post_tags pt JOIN tags t ON t.id = pt.tag_id WHERE t.name = ?))
+         AND (? IS NULL OR category_id IN (
+            WITH RECURSIVE tree AS (
+                SELECT id FROM categories WHERE slug = ?
+                UNION ALL SELECT c.id FROM categories c JOIN tree ON c.parent_id = tree.id)
+            SELECT id FROM tree))",
+        viewer_id,
+        viewer_id,
+        viewer_id,
+        tag,
+        tag,
+        category,
+        category
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Error while fetching the count from the database".to_string(),
+            }),
+        )
+    })?;
 
     // Calculate total pages
     let total_pages = if total_items > 0 {
@@ -284,21 +490,10 @@ pub async fn get_posts(
     };
 
     // Map over the fetched rows to create Post instances
-    let posts: Vec<Post> = query
-        .iter()
-        .map(|row| {
-            // Adjust if you're using NaiveDateTime or OffsetDateTime
-            let created_at: DateTime<Utc> =
-                timestamp_to_datetime!(row).expect("Failed to parse date");
-            Post {
-                id: row.id,
-                author_id: row.author_id,
-                title: row.title.clone(),
-                body: row.body.clone(),
-                created_at,
-            }
-        })
-        .collect();
+    let mut posts: Vec<Post> = query.iter().map(|row| post_from_row!(row)).collect();
+    attach_tags(db_pool.inner(), &mut posts)
+        .await
+        .map_err(db_error)?;
 
     // Return the paginated response
     Ok(Json(PagedResponse {
@@ -309,3 +504,316 @@ pub async fn get_posts(
         data: posts,
     }))
 }
+
+// the author's own posts in any state, optionally narrowed to one
+#[get("/me/posts?<status>&<pagination..>")]
+pub async fn get_my_posts(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsRead>,
+    status: Option<PostStatus>,
+    pagination: Option<Pagination>,
+) -> Result<Json<PagedResponse<Post>>, status::Custom<Json<ResponseError>>> {
+    let page = pagination.as_ref().map_or(1, |p| p.page.unwrap_or(1)) as i64;
+    let size = pagination.as_ref().map_or(10, |p| p.size.unwrap_or(10)) as i64;
+    let size = size.max(1);
+    let offset = (page - 1) * size;
+    let status_filter = status.map(|status| status.as_str());
+
+    let query = sqlx::query!(
+        "SELECT * FROM posts WHERE author_id = ? AND deleted_at IS NULL AND (? IS NULL OR status = ?)
+         ORDER BY created_at DESC LIMIT ? OFFSET ?",
+        user.user_id,
+        status_filter,
+        status_filter,
+        size,
+        offset
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Error while fetching data from the database".to_string(),
+            }),
+        )
+    })?;
+
+    let total_items = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM posts WHERE author_id = ? AND deleted_at IS NULL
+         AND (? IS NULL OR status = ?)",
+        user.user_id,
+        status_filter,
+        status_filter
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Error while fetching the count from the database".to_string(),
+            }),
+        )
+    })?;
+    let total_pages = if total_items > 0 {
+        (total_items + size - 1) / size
+    } else {
+        0
+    };
+
+    let mut posts: Vec<Post> = query.iter().map(|row| post_from_row!(row)).collect();
+    attach_tags(db_pool.inner(), &mut posts)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(PagedResponse {
+        current_page: page,
+        page_size: size,
+        total_items,
+        total_pages,
+        data: posts,
+    }))
+}
+
+// moves a post to `new_status`, with the same permissions as editing it
+async fn set_post_status(
+    db_pool: &rocket::State<Db>,
+    user_id: i64,
+    id: i64,
+    new_status: PostStatus,
+    publish_at: Option<DateTime<Utc>>,
+    // only moves the post while it is in one of these, empty for any status
+    current_statuses: &[PostStatus],
+) -> Result<Json<Post>, status::Custom<Json<ResponseError>>> {
+    let author_id = sqlx::query_scalar!(
+        "SELECT author_id FROM posts WHERE id = ? AND deleted_at IS NULL",
+        id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| {
+        status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "Post not found".to_string(),
+            }),
+        )
+    })?;
+
+    require_ownership_permission(
+        db_pool.inner(),
+        user_id,
+        author_id as i64,
+        POST_UPDATE_OWN,
+        POST_UPDATE_ANY,
+    )
+    .await?;
+
+    // published_at keeps the first publication time when a post is republished.
+    // the status check happens in the same statement, the scheduler may be moving the post too
+    let mut query = QueryBuilder::<MySql>::new("UPDATE posts SET status = ");
+    query.push_bind(new_status.as_str());
+    query.push(", publish_at = FROM_UNIXTIME(");
+    query.push_bind(publish_at.map(|publish_at| publish_at.timestamp()));
+    query.push("), published_at = IF(");
+    query.push_bind(new_status.as_str());
+    query.push(" = 'published', COALESCE(published_at, NOW()), published_at) WHERE id = ");
+    query.push_bind(id);
+    query.push(" AND deleted_at IS NULL");
+    if !current_statuses.is_empty() {
+        query.push(" AND status IN (");
+        let mut statuses = query.separated(", ");
+        for status in current_statuses {
+            statuses.push_bind(status.as_str());
+        }
+        statuses.push_unseparated(")");
+    }
+    let result = query.build().execute(db_pool.inner()).await.map_err(|_| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Failed to update post".to_string(),
+            }),
+        )
+    })?;
+    if !current_statuses.is_empty() && result.rows_affected() == 0 {
+        let statuses = current_statuses
+            .iter()
+            .map(|status| status.as_str())
+            .collect::<Vec<_>>()
+            .join(" or ");
+        return Err(status::Custom(
+            Status::Conflict,
+            Json(ResponseError {
+                error: format!("Post is not {}", statuses),
+            }),
+        ));
+    }
+
+    let record = sqlx::query!(
+        "SELECT * FROM posts WHERE id = ? AND deleted_at IS NULL",
+        id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    let mut post = post_from_row!(record);
+    attach_tags(db_pool.inner(), std::slice::from_mut(&mut post))
+        .await
+        .map_err(db_error)?;
+    Ok(Json(post))
+}
+
+#[post("/post/<id>/publish")]
+pub async fn publish_post(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsWrite>,
+    id: i64,
+) -> Result<Json<Post>, status::Custom<Json<ResponseError>>> {
+    set_post_status(db_pool, user.user_id, id, PostStatus::Published, None, &[]).await
+}
+
+// back to a draft, only the author sees it again
+#[post("/post/<id>/unpublish")]
+pub async fn unpublish_post(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsWrite>,
+    id: i64,
+) -> Result<Json<Post>, status::Custom<Json<ResponseError>>> {
+    set_post_status(db_pool, user.user_id, id, PostStatus::Draft, None, &[]).await
+}
+
+#[post("/post/<id>/archive")]
+pub async fn archive_post(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsWrite>,
+    id: i64,
+) -> Result<Json<Post>, status::Custom<Json<ResponseError>>> {
+    set_post_status(db_pool, user.user_id, id, PostStatus::Archived, None, &[]).await
+}
+
+// the scheduler publishes the post once `publish_at` has passed
+#[put("/post/<id>/schedule", data = "<schedule>")]
+pub async fn schedule_post(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsWrite>,
+    id: i64,
+    schedule: Json<SchedulePost>,
+) -> Result<Json<Post>, status::Custom<Json<ResponseError>>> {
+    if schedule.publish_at <= Utc::now() {
+        return Err(status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "publish_at must be in the future".to_string(),
+            }),
+        ));
+    }
+    set_post_status(
+        db_pool,
+        user.user_id,
+        id,
+        PostStatus::Scheduled,
+        Some(schedule.publish_at),
+        // a published or archived post would go offline until the scheduler runs
+        &[PostStatus::Draft, PostStatus::Scheduled],
+    )
+    .await
+}
+
+// cancelling leaves the post as a draft
+#[delete("/post/<id>/schedule")]
+pub async fn unschedule_post(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsWrite>,
+    id: i64,
+) -> Result<Json<Post>, status::Custom<Json<ResponseError>>> {
+    set_post_status(
+        db_pool,
+        user.user_id,
+        id,
+        PostStatus::Draft,
+        None,
+        &[PostStatus::Scheduled],
+    )
+    .await
+}
+
+// upcoming posts, editors who may update any post see everyone's
+#[get("/post/scheduled")]
+pub async fn get_scheduled_posts(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsRead>,
+) -> Result<Json<Vec<Post>>, status::Custom<Json<ResponseError>>> {
+    let see_all = has_permission(db_pool.inner(), user.user_id, POST_UPDATE_ANY)
+        .await
+        .map_err(db_error)?;
+    let query = sqlx::query!(
+        "SELECT * FROM posts WHERE status = 'scheduled' AND deleted_at IS NULL AND (? OR author_id = ?)
+         ORDER BY publish_at",
+        see_all,
+        user.user_id
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let mut posts: Vec<Post> = query.iter().map(|row| post_from_row!(row)).collect();
+    attach_tags(db_pool.inner(), &mut posts)
+        .await
+        .map_err(db_error)?;
+    Ok(Json(posts))
+}
+
+// public lookup by slug, authors can also reach their unpublished posts
+#[get("/post/by-slug/<slug>")]
+pub async fn get_post_by_slug(
+    db_pool: &rocket::State<Db>,
+    viewer: Option<JwtAuth>,
+    slug: &str,
+) -> Result<HandleResponse<Post>, status::Custom<Json<ResponseError>>> {
+    let not_found = || {
+        status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "Post not found".to_string(),
+            }),
+        )
+    };
+    let (post_id, moved_to) = match resolve_slug(db_pool.inner(), slug)
+        .await
+        .map_err(db_error)?
+    {
+        Some(SlugLookup::Current(post_id)) => (post_id, None),
+        Some(SlugLookup::Moved(post_id, slug)) => (post_id, Some(slug)),
+        None => return Err(not_found()),
+    };
+
+    let viewer_id = viewer.and_then(|viewer| viewer.claims.sub.parse::<i64>().ok());
+    let record = sqlx::query!(
+        "SELECT * FROM posts WHERE id = ? AND deleted_at IS NULL
+         AND (status = 'published' OR author_id = ?)",
+        post_id,
+        viewer_id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_found)?;
+
+    // an old slug only redirects for viewers allowed to see the post, the new slug of a
+    // draft must not leak
+    if let Some(slug) = moved_to {
+        return Ok(HandleResponse::Moved(Redirect::moved(format!(
+            "/post/by-slug/{}",
+            slug
+        ))));
+    }
+
+    let mut post = post_from_row!(record);
+    attach_tags(db_pool.inner(), std::slice::from_mut(&mut post))
+        .await
+        .map_err(db_error)?;
+    Ok(HandleResponse::Found(Json(post)))
+}
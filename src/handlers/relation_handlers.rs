@@ -0,0 +1,188 @@
+use blog_api::timestamp_to_datetime;
+use chrono::{DateTime, Utc};
+use rocket::{http::Status, response::status, serde::json::Json};
+
+use crate::{
+    db::Db,
+    guards::jwt_guard::JwtAuth,
+    models::{
+        error::{db_error, ResponseError},
+        user::RelatedUser,
+    },
+};
+
+// the target has to be another, existing user
+async fn check_target(
+    db_pool: &rocket::State<Db>,
+    user_id: i64,
+    id: i64,
+) -> Result<(), status::Custom<Json<ResponseError>>> {
+    if user_id == id {
+        return Err(status::Custom(
+            Status::BadRequest,
+            Json(ResponseError {
+                error: "You cannot do this to yourself".to_string(),
+            }),
+        ));
+    }
+    let exists = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE id = ? AND deleted_at IS NULL)",
+        id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    if exists == 0 {
+        return Err(status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "User not found".to_string(),
+            }),
+        ));
+    }
+    Ok(())
+}
+
+// hides the user's comments from the blocker and stops them commenting on the blocker's posts
+#[post("/<id>/block")]
+pub async fn block_user(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    id: i64,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    check_target(db_pool, user_id, id).await?;
+
+    let mut tx = db_pool.begin().await.map_err(db_error)?;
+    sqlx::query!(
+        "INSERT IGNORE INTO blocks (blocker_id, blocked_id) VALUES (?, ?)",
+        user_id,
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    // a block ends following in both directions
+    sqlx::query!(
+        "DELETE FROM follows WHERE (follower_id = ? AND followee_id = ?) OR (follower_id = ? AND followee_id = ?)",
+        user_id,
+        id,
+        id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+#[delete("/<id>/block")]
+pub async fn unblock_user(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    id: i64,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    sqlx::query!(
+        "DELETE FROM blocks WHERE blocker_id = ? AND blocked_id = ?",
+        user_id,
+        id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+#[get("/me/blocks")]
+pub async fn get_blocks(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+) -> Result<Json<Vec<RelatedUser>>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let query = sqlx::query!(
+        "SELECT u.id, u.username, u.handle, b.created_at FROM blocks b JOIN users u ON u.id = b.blocked_id
+         WHERE b.blocker_id = ? ORDER BY b.created_at DESC",
+        user_id
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let blocks = query
+        .into_iter()
+        .map(|row| RelatedUser {
+            since: timestamp_to_datetime!(row).expect("faild to parse date"),
+            id: row.id,
+            username: row.username,
+            handle: row.handle,
+        })
+        .collect();
+    Ok(Json(blocks))
+}
+
+// hides the user's posts from the muter's own listings and feed, nothing else changes
+#[post("/<id>/mute")]
+pub async fn mute_user(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    id: i64,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    check_target(db_pool, user_id, id).await?;
+    sqlx::query!(
+        "INSERT IGNORE INTO mutes (muter_id, muted_id) VALUES (?, ?)",
+        user_id,
+        id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+#[delete("/<id>/mute")]
+pub async fn unmute_user(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    id: i64,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    sqlx::query!(
+        "DELETE FROM mutes WHERE muter_id = ? AND muted_id = ?",
+        user_id,
+        id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+#[get("/me/mutes")]
+pub async fn get_mutes(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+) -> Result<Json<Vec<RelatedUser>>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let query = sqlx::query!(
+        "SELECT u.id, u.username, u.handle, m.created_at FROM mutes m JOIN users u ON u.id = m.muted_id
+         WHERE m.muter_id = ? ORDER BY m.created_at DESC",
+        user_id
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let mutes = query
+        .into_iter()
+        .map(|row| RelatedUser {
+            since: timestamp_to_datetime!(row).expect("faild to parse date"),
+            id: row.id,
+            username: row.username,
+            handle: row.handle,
+        })
+        .collect();
+    Ok(Json(mutes))
+}
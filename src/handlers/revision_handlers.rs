@@ -0,0 +1,162 @@
+use blog_api::timestamp_to_datetime;
+use chrono::{DateTime, Utc};
+use rocket::{http::Status, response::status, serde::json::Json};
+
+use crate::{
+    auth::permissions::{require_ownership_permission, POST_UPDATE_ANY, POST_UPDATE_OWN},
+    db::Db,
+    guards::scope_guard::{PostsRead, PostsWrite, ScopeAuth},
+    models::{
+        error::{db_error, ResponseError},
+        revision::{DiffMode, Revision, RevisionDiff, RevisionSummary},
+    },
+    revisions::{diff_text, revise_post},
+};
+
+fn not_found(error: &str) -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::NotFound,
+        Json(ResponseError {
+            error: error.to_string(),
+        }),
+    )
+}
+
+// a post's history is visible to whoever may edit it
+async fn check_post_access(
+    db_pool: &Db,
+    user_id: i64,
+    post_id: i64,
+) -> Result<(), status::Custom<Json<ResponseError>>> {
+    let author_id = sqlx::query_scalar!(
+        "SELECT author_id FROM posts WHERE id = ? AND deleted_at IS NULL",
+        post_id
+    )
+    .fetch_optional(db_pool)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| not_found("Post not found"))?;
+    require_ownership_permission(
+        db_pool,
+        user_id,
+        author_id as i64,
+        POST_UPDATE_OWN,
+        POST_UPDATE_ANY,
+    )
+    .await
+}
+
+async fn fetch_revision(
+    db_pool: &Db,
+    post_id: i64,
+    rev_id: i64,
+) -> Result<Revision, status::Custom<Json<ResponseError>>> {
+    let row = sqlx::query!(
+        "SELECT * FROM post_revisions WHERE id = ? AND post_id = ?",
+        rev_id,
+        post_id
+    )
+    .fetch_optional(db_pool)
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| not_found("Revision not found"))?;
+    Ok(Revision {
+        id: row.id,
+        post_id: row.post_id,
+        editor_id: row.editor_id,
+        title: row.title,
+        body: row.body,
+        created_at: timestamp_to_datetime!(row).unwrap(),
+    })
+}
+
+// newest first, without the bodies
+#[get("/post/<id>/revisions")]
+pub async fn get_revisions(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsRead>,
+    id: i64,
+) -> Result<Json<Vec<RevisionSummary>>, status::Custom<Json<ResponseError>>> {
+    check_post_access(db_pool.inner(), user.user_id, id).await?;
+
+    let rows = sqlx::query!(
+        "SELECT id, editor_id, title, created_at FROM post_revisions
+         WHERE post_id = ? ORDER BY id DESC",
+        id
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let revisions = rows
+        .into_iter()
+        .map(|row| RevisionSummary {
+            id: row.id,
+            editor_id: row.editor_id,
+            title: row.title,
+            created_at: timestamp_to_datetime!(row).unwrap(),
+        })
+        .collect();
+    Ok(Json(revisions))
+}
+
+#[get("/post/<id>/revisions/<rev_id>")]
+pub async fn get_revision(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsRead>,
+    id: i64,
+    rev_id: i64,
+) -> Result<Json<Revision>, status::Custom<Json<ResponseError>>> {
+    check_post_access(db_pool.inner(), user.user_id, id).await?;
+    let revision = fetch_revision(db_pool.inner(), id, rev_id).await?;
+    Ok(Json(revision))
+}
+
+// changes from one revision to another, by line unless `mode=word`
+#[get("/post/<id>/revisions/diff?<from>&<to>&<mode>")]
+pub async fn diff_revisions(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsRead>,
+    id: i64,
+    from: i64,
+    to: i64,
+    mode: Option<DiffMode>,
+) -> Result<Json<RevisionDiff>, status::Custom<Json<ResponseError>>> {
+    check_post_access(db_pool.inner(), user.user_id, id).await?;
+    let old = fetch_revision(db_pool.inner(), id, from).await?;
+    let new = fetch_revision(db_pool.inner(), id, to).await?;
+    let mode = mode.unwrap_or(DiffMode::Line);
+
+    Ok(Json(RevisionDiff {
+        from,
+        to,
+        title: diff_text(&old.title, &new.title, mode),
+        body: diff_text(&old.body, &new.body, mode),
+    }))
+}
+
+// the old title and body become the post's content again, as a new revision on top
+#[post("/post/<id>/revisions/<rev_id>/restore")]
+pub async fn restore_revision(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsWrite>,
+    id: i64,
+    rev_id: i64,
+) -> Result<Json<Revision>, status::Custom<Json<ResponseError>>> {
+    check_post_access(db_pool.inner(), user.user_id, id).await?;
+    let revision = fetch_revision(db_pool.inner(), id, rev_id).await?;
+
+    let mut tx = db_pool.begin().await.map_err(db_error)?;
+    revise_post(&mut tx, id, user.user_id, &revision.title, &revision.body)
+        .await
+        .map_err(db_error)?;
+    let new_id = sqlx::query_scalar!("SELECT MAX(id) FROM post_revisions WHERE post_id = ?", id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| not_found("Revision not found"))?;
+    tx.commit().await.map_err(db_error)?;
+
+    let restored = fetch_revision(db_pool.inner(), id, new_id as i64).await?;
+    Ok(Json(restored))
+}
@@ -0,0 +1,139 @@
+use rocket::{http::Status, response::status, serde::json::Json};
+
+use crate::{
+    db::Db,
+    guards::jwt_guard::JwtAuth,
+    models::{
+        error::{db_error, ResponseError},
+        post::Pagination,
+        search::{SearchHit, SearchHitKind},
+        PagedResponse,
+    },
+    search::{parse_query, snippet, MAX_QUERY_LENGTH, MIN_TERM_LENGTH},
+};
+
+fn bad_request(error: &str) -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::BadRequest,
+        Json(ResponseError {
+            error: error.to_string(),
+        }),
+    )
+}
+
+// published posts, and their comments when `comments` is set, best matches first.
+// title matches count twice, signed in callers do not see authors they blocked
+#[get("/search?<q>&<comments>&<pagination..>")]
+pub async fn search(
+    db_pool: &rocket::State<Db>,
+    viewer: Option<JwtAuth>,
+    q: Option<String>,
+    comments: Option<bool>,
+    pagination: Option<Pagination>,
+) -> Result<Json<PagedResponse<SearchHit>>, status::Custom<Json<ResponseError>>> {
+    let q = q.unwrap_or_default();
+    if q.chars().count() > MAX_QUERY_LENGTH {
+        return Err(bad_request(&format!(
+            "Search query must be at most {} characters",
+            MAX_QUERY_LENGTH
+        )));
+    }
+    let query = parse_query(&q).ok_or_else(|| {
+        bad_request(&format!(
+            "Search query needs a word of at least {} characters",
+            MIN_TERM_LENGTH
+        ))
+    })?;
+    let include_comments = comments.unwrap_or(false);
+    let viewer_id = viewer.and_then(|viewer| viewer.claims.sub.parse::<i64>().ok());
+
+    let page = pagination.as_ref().map_or(1, |p| p.page.unwrap_or(1)) as i64;
+    let size = pagination.as_ref().map_or(10, |p| p.size.unwrap_or(10)) as i64;
+    let size = size.clamp(1, 100);
+    let offset = (page.max(1) - 1) * size;
+
+    let rows = sqlx::query!(
+        r#"SELECT 'post' AS "kind!: String", p.id AS "id!: i32", p.id AS "post_id!: i32",
+            p.title AS "post_title!: String", p.slug AS "post_slug!: String", p.body AS "text!: String",
+            MATCH(p.title, p.body) AGAINST (? IN BOOLEAN MODE)
+                + MATCH(p.title) AGAINST (? IN BOOLEAN MODE) AS "score!: f64"
+         FROM posts p
+         WHERE p.status = 'published' AND p.deleted_at IS NULL AND MATCH(p.title, p.body) AGAINST (? IN BOOLEAN MODE)
+         AND (? IS NULL OR p.author_id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?))
+         UNION ALL
+         SELECT 'comment', c.id, c.post_id, p.title, p.slug, c.body,
+            MATCH(c.body) AGAINST (? IN BOOLEAN MODE)
+         FROM comments c JOIN posts p ON p.id = c.post_id
+         WHERE ? AND c.deleted_at IS NULL AND p.status = 'published' AND p.deleted_at IS NULL
+         AND MATCH(c.body) AGAINST (? IN BOOLEAN MODE)
+         AND (? IS NULL OR c.author_id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?))
+         ORDER BY 7 DESC, 2 DESC LIMIT ? OFFSET ?"#,
+        query.boolean,
+        query.boolean,
+        query.boolean,
+        viewer_id,
+        viewer_id,
+        query.boolean,
+        include_comments,
+        query.boolean,
+        viewer_id,
+        viewer_id,
+        size,
+        offset
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let total_items = sqlx::query_scalar!(
+        r#"SELECT
+            (SELECT COUNT(*) FROM posts p
+             WHERE p.status = 'published' AND p.deleted_at IS NULL AND MATCH(p.title, p.body) AGAINST (? IN BOOLEAN MODE)
+             AND (? IS NULL OR p.author_id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?)))
+          + (SELECT COUNT(*) FROM comments c JOIN posts p ON p.id = c.post_id
+             WHERE ? AND c.deleted_at IS NULL AND p.status = 'published' AND p.deleted_at IS NULL
+         AND MATCH(c.body) AGAINST (? IN BOOLEAN MODE)
+             AND (? IS NULL OR c.author_id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?)))
+          AS "total!: i64""#,
+        query.boolean,
+        viewer_id,
+        viewer_id,
+        include_comments,
+        query.boolean,
+        viewer_id,
+        viewer_id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    let total_pages = if total_items > 0 {
+        (total_items + size - 1) / size
+    } else {
+        0
+    };
+
+    let hits = rows
+        .into_iter()
+        .map(|row| SearchHit {
+            kind: if row.kind == "comment" {
+                SearchHitKind::Comment
+            } else {
+                SearchHitKind::Post
+            },
+            id: row.id,
+            post_id: row.post_id,
+            post_title: row.post_title,
+            post_slug: row.post_slug,
+            snippet: snippet(&row.text, &query.terms),
+            score: row.score,
+        })
+        .collect();
+
+    Ok(Json(PagedResponse {
+        current_page: page,
+        page_size: size,
+        total_items,
+        total_pages,
+        data: hits,
+    }))
+}
@@ -0,0 +1,241 @@
+use rocket::{http::Status, response::status, serde::json::Json};
+
+use crate::{
+    db::Db,
+    guards::role_guard::{ManageTaxonomy, RoleAuth},
+    models::{
+        error::{db_error, ResponseError},
+        taxonomy::{Category, MergeTag, NewCategory, RenameTag, TagCount},
+    },
+    slug::slugify,
+    taxonomy::{build_category_tree, category_exists, normalize_tag, validate_tag},
+};
+
+fn bad_request(error: &str) -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::BadRequest,
+        Json(ResponseError {
+            error: error.to_string(),
+        }),
+    )
+}
+
+fn tag_not_found() -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::NotFound,
+        Json(ResponseError {
+            error: "Tag not found".to_string(),
+        }),
+    )
+}
+
+#[get("/tags")]
+pub async fn get_tags(
+    db_pool: &rocket::State<Db>,
+) -> Result<Json<Vec<TagCount>>, status::Custom<Json<ResponseError>>> {
+    let query = sqlx::query!(
+        r#"SELECT t.name, COUNT(p.id) AS "post_count!" FROM tags t
+         LEFT JOIN post_tags pt ON pt.tag_id = t.id
+         LEFT JOIN posts p ON p.id = pt.post_id AND p.status = 'published' AND p.deleted_at IS NULL
+         GROUP BY t.id, t.name ORDER BY 2 DESC, t.name"#
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let tags = query
+        .into_iter()
+        .map(|row| TagCount {
+            name: row.name,
+            post_count: row.post_count,
+        })
+        .collect();
+    Ok(Json(tags))
+}
+
+// renaming onto an existing tag is refused, merge them instead
+#[put("/tags/<name>", data = "<request>")]
+pub async fn rename_tag(
+    db_pool: &rocket::State<Db>,
+    _editor: RoleAuth<ManageTaxonomy>,
+    name: &str,
+    request: Json<RenameTag>,
+) -> Result<Json<String>, status::Custom<Json<ResponseError>>> {
+    let new_name = normalize_tag(&request.name);
+    validate_tag(&new_name).map_err(|e| bad_request(&e))?;
+
+    let result = sqlx::query!(
+        "UPDATE tags SET name = ? WHERE name = ?",
+        new_name,
+        normalize_tag(name)
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(|e| match e.as_database_error() {
+        Some(e) if e.is_unique_violation() => status::Custom(
+            Status::Conflict,
+            Json(ResponseError {
+                error: "A tag with this name already exists, merge the tags instead".to_string(),
+            }),
+        ),
+        _ => db_error(e),
+    })?;
+    if result.rows_affected() == 0 {
+        return Err(tag_not_found());
+    }
+    Ok(Json("Tag renamed".to_string()))
+}
+
+// moves every post of `name` to `into` and removes `name`
+#[post("/tags/<name>/merge", data = "<request>")]
+pub async fn merge_tag(
+    db_pool: &rocket::State<Db>,
+    _editor: RoleAuth<ManageTaxonomy>,
+    name: &str,
+    request: Json<MergeTag>,
+) -> Result<Json<String>, status::Custom<Json<ResponseError>>> {
+    let source = normalize_tag(name);
+    let target = normalize_tag(&request.into);
+    if source == target {
+        return Err(bad_request("Cannot merge a tag into itself"));
+    }
+
+    let mut tx = db_pool.begin().await.map_err(db_error)?;
+    let source_id = sqlx::query_scalar!("SELECT id FROM tags WHERE name = ? FOR UPDATE", source)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(tag_not_found)?;
+    let target_id = sqlx::query_scalar!("SELECT id FROM tags WHERE name = ? FOR UPDATE", target)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(tag_not_found)?;
+
+    // posts that already carry both tags keep a single row
+    sqlx::query!(
+        "INSERT IGNORE INTO post_tags (post_id, tag_id) SELECT post_id, ? FROM post_tags WHERE tag_id = ?",
+        target_id,
+        source_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    sqlx::query!("DELETE FROM tags WHERE id = ?", source_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+    Ok(Json("Tags merged".to_string()))
+}
+
+// the whole tree, top level categories first
+#[get("/categories")]
+pub async fn get_categories(
+    db_pool: &rocket::State<Db>,
+) -> Result<Json<Vec<Category>>, status::Custom<Json<ResponseError>>> {
+    let categories = sqlx::query!("SELECT id, name, slug, parent_id FROM categories ORDER BY name")
+        .fetch_all(db_pool.inner())
+        .await
+        .map_err(db_error)?
+        .into_iter()
+        .map(|row| Category {
+            id: row.id,
+            name: row.name,
+            slug: row.slug,
+            parent_id: row.parent_id,
+            children: Vec::new(),
+        })
+        .collect();
+    Ok(Json(build_category_tree(categories)))
+}
+
+#[post("/categories", data = "<new_category>")]
+pub async fn create_category(
+    db_pool: &rocket::State<Db>,
+    _editor: RoleAuth<ManageTaxonomy>,
+    new_category: Json<NewCategory>,
+) -> Result<status::Custom<Json<Category>>, status::Custom<Json<ResponseError>>> {
+    let name = new_category.name.trim();
+    if name.is_empty() || name.chars().count() > 80 {
+        return Err(bad_request(
+            "Category name must be between 1 and 80 characters",
+        ));
+    }
+    if let Some(parent_id) = new_category.parent_id {
+        if !category_exists(db_pool.inner(), parent_id)
+            .await
+            .map_err(db_error)?
+        {
+            return Err(bad_request("Unknown parent category"));
+        }
+    }
+
+    let slug = slugify(name);
+    let result = sqlx::query!(
+        "INSERT INTO categories (name, slug, parent_id) VALUES (?, ?, ?)",
+        name,
+        slug,
+        new_category.parent_id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(|e| match e.as_database_error() {
+        Some(e) if e.is_unique_violation() => status::Custom(
+            Status::Conflict,
+            Json(ResponseError {
+                error: "A category with this name already exists".to_string(),
+            }),
+        ),
+        _ => db_error(e),
+    })?;
+
+    Ok(status::Custom(
+        Status::Created,
+        Json(Category {
+            id: result.last_insert_id() as i32,
+            name: name.to_string(),
+            slug,
+            parent_id: new_category.parent_id.map(|parent_id| parent_id as i32),
+            children: Vec::new(),
+        }),
+    ))
+}
+
+// posts in the category become uncategorised, subcategories have to go first
+#[delete("/categories/<id>")]
+pub async fn delete_category(
+    db_pool: &rocket::State<Db>,
+    _editor: RoleAuth<ManageTaxonomy>,
+    id: i64,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let has_children = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM categories WHERE parent_id = ?)",
+        id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    if has_children != 0 {
+        return Err(status::Custom(
+            Status::Conflict,
+            Json(ResponseError {
+                error: "Delete or move the subcategories first".to_string(),
+            }),
+        ));
+    }
+
+    let result = sqlx::query!("DELETE FROM categories WHERE id = ?", id)
+        .execute(db_pool.inner())
+        .await
+        .map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err(status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "Category not found".to_string(),
+            }),
+        ));
+    }
+    Ok(status::Custom(Status::NoContent, ()))
+}
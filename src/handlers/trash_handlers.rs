@@ -0,0 +1,146 @@
+use blog_api::timestamp_to_datetime;
+use chrono::{DateTime, Duration, Utc};
+use rocket::{response::status, serde::json::Json};
+
+use crate::{
+    auth::permissions::{has_permission, COMMENT_MODERATE, POST_DELETE_ANY},
+    config::trash_retention_days,
+    db::Db,
+    guards::scope_guard::{CommentsRead, PostsRead, ScopeAuth},
+    models::{
+        error::{db_error, ResponseError},
+        post::{Pagination, PostStatus},
+        trash::{TrashedComment, TrashedPost},
+        PagedResponse,
+    },
+};
+
+// (page, size, offset) from the optional pagination params
+fn page_bounds(pagination: Option<Pagination>) -> (i64, i64, i64) {
+    let page = pagination.as_ref().map_or(1, |p| p.page.unwrap_or(1)) as i64;
+    let size = pagination.as_ref().map_or(10, |p| p.size.unwrap_or(10)) as i64;
+    let page = page.max(1);
+    let size = size.clamp(1, 100);
+    (page, size, (page - 1) * size)
+}
+
+fn total_pages(total_items: i64, size: i64) -> i64 {
+    (total_items + size - 1) / size
+}
+
+// the caller's deleted posts, users who may delete any post see everyone's
+#[get("/post/trash?<pagination..>")]
+pub async fn get_trashed_posts(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<PostsRead>,
+    pagination: Option<Pagination>,
+) -> Result<Json<PagedResponse<TrashedPost>>, status::Custom<Json<ResponseError>>> {
+    let see_all = has_permission(db_pool.inner(), user.user_id, POST_DELETE_ANY)
+        .await
+        .map_err(db_error)?;
+    let (page, size, offset) = page_bounds(pagination);
+
+    let rows = sqlx::query!(
+        "SELECT id, author_id, title, slug, status, deleted_at FROM posts
+         WHERE deleted_at IS NOT NULL AND (? OR author_id = ?)
+         ORDER BY deleted_at DESC LIMIT ? OFFSET ?",
+        see_all,
+        user.user_id,
+        size,
+        offset
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    let total_items = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM posts WHERE deleted_at IS NOT NULL AND (? OR author_id = ?)",
+        see_all,
+        user.user_id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let retention = Duration::days(trash_retention_days());
+    let posts = rows
+        .into_iter()
+        .map(|row| {
+            let deleted_at = timestamp_to_datetime!(row, deleted_at).unwrap();
+            TrashedPost {
+                id: row.id,
+                author_id: row.author_id,
+                title: row.title,
+                slug: row.slug,
+                status: PostStatus::parse(&row.status).unwrap_or(PostStatus::Draft),
+                deleted_at,
+                purge_at: deleted_at + retention,
+            }
+        })
+        .collect();
+
+    Ok(Json(PagedResponse {
+        data: posts,
+        total_pages: total_pages(total_items, size),
+        total_items,
+        current_page: page,
+        page_size: size,
+    }))
+}
+
+// the caller's deleted comments, moderators see everyone's
+#[get("/comment/trash?<pagination..>")]
+pub async fn get_trashed_comments(
+    db_pool: &rocket::State<Db>,
+    user: ScopeAuth<CommentsRead>,
+    pagination: Option<Pagination>,
+) -> Result<Json<PagedResponse<TrashedComment>>, status::Custom<Json<ResponseError>>> {
+    let see_all = has_permission(db_pool.inner(), user.user_id, COMMENT_MODERATE)
+        .await
+        .map_err(db_error)?;
+    let (page, size, offset) = page_bounds(pagination);
+
+    let rows = sqlx::query!(
+        "SELECT id, post_id, author_id, body, deleted_at FROM comments
+         WHERE deleted_at IS NOT NULL AND (? OR author_id = ?)
+         ORDER BY deleted_at DESC LIMIT ? OFFSET ?",
+        see_all,
+        user.user_id,
+        size,
+        offset
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    let total_items = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM comments WHERE deleted_at IS NOT NULL AND (? OR author_id = ?)",
+        see_all,
+        user.user_id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let retention = Duration::days(trash_retention_days());
+    let comments = rows
+        .into_iter()
+        .map(|row| {
+            let deleted_at = timestamp_to_datetime!(row, deleted_at).unwrap();
+            TrashedComment {
+                id: row.id,
+                post_id: row.post_id,
+                author_id: row.author_id,
+                body: row.body,
+                deleted_at,
+                purge_at: deleted_at + retention,
+            }
+        })
+        .collect();
+
+    Ok(Json(PagedResponse {
+        data: comments,
+        total_pages: total_pages(total_items, size),
+        total_items,
+        current_page: page,
+        page_size: size,
+    }))
+}
@@ -0,0 +1,324 @@
+use std::net::IpAddr;
+
+use blog_api::timestamp_to_datetime;
+use chrono::{DateTime, Utc};
+use rocket::{
+    http::{CookieJar, Status},
+    response::status,
+    serde::json::Json,
+};
+
+use crate::{
+    auth::{
+        jwt::verify_two_factor_challenge,
+        keys::Keyring,
+        throttle::{clear_failed_sign_ins, lockout_remaining, record_failed_sign_in},
+        totp::{
+            accept_code, build_totp, claim_challenge_attempt, consume_challenge,
+            generate_recovery_codes, generate_secret, store_recovery_codes, verify_second_factor,
+        },
+    },
+    avatar::avatar_urls,
+    db::Db,
+    guards::jwt_guard::JwtAuth,
+    handlers::auth_handlers::start_session,
+    models::{
+        error::{db_error, ResponseError, SignInError, TooManyRequests},
+        two_factor::{RecoveryCodes, TwoFactorCode, TwoFactorSetup, TwoFactorVerification},
+        user::User,
+    },
+};
+
+fn invalid_code() -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::Unauthorized,
+        Json(ResponseError {
+            error: "Invalid two-factor code".to_string(),
+        }),
+    )
+}
+
+// the same keys the sign in lockout uses
+fn throttle_keys(email: &str, client_ip: Option<IpAddr>) -> (String, String) {
+    let ip = client_ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+    (email.trim().to_lowercase(), ip)
+}
+
+// wrong second factors count towards the same lockout as wrong passwords
+async fn ensure_not_locked_out(db_pool: &Db, email: &str, ip: &str) -> Result<(), SignInError> {
+    if let Some(retry_after) = lockout_remaining(db_pool, email, ip)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(SignInError::TooManyRequests(TooManyRequests::new(
+            "Too many failed attempts, try again later",
+            retry_after,
+        )));
+    }
+    Ok(())
+}
+
+fn not_enabled() -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::BadRequest,
+        Json(ResponseError {
+            error: "Two-factor authentication is not enabled".to_string(),
+        }),
+    )
+}
+
+// start enrollment, the secret only becomes active after /2fa/confirm
+#[post("/2fa/enroll")]
+pub async fn enroll_two_factor(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+) -> Result<Json<TwoFactorSetup>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let record = sqlx::query!(
+        "SELECT email, totp_enabled_at FROM users WHERE id = ?",
+        user_id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    if record.totp_enabled_at.is_some() {
+        return Err(status::Custom(
+            Status::Conflict,
+            Json(ResponseError {
+                error: "Two-factor authentication is already enabled".to_string(),
+            }),
+        ));
+    }
+
+    let secret = generate_secret();
+    let totp = build_totp(&secret, &record.email).ok_or_else(|| {
+        status::Custom(
+            Status::InternalServerError,
+            Json(ResponseError {
+                error: "Failed to create two-factor secret".to_string(),
+            }),
+        )
+    })?;
+    sqlx::query!(
+        "UPDATE users SET totp_secret = ?, totp_last_step = NULL WHERE id = ?",
+        secret,
+        user_id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(TwoFactorSetup {
+        secret,
+        provisioning_uri: totp.get_url(),
+    }))
+}
+
+#[post("/2fa/confirm", data = "<code>")]
+pub async fn confirm_two_factor(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    code: Json<TwoFactorCode>,
+) -> Result<Json<RecoveryCodes>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let record = sqlx::query!(
+        "SELECT totp_secret, totp_enabled_at FROM users WHERE id = ?",
+        user_id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    let secret: String = match (record.totp_secret, record.totp_enabled_at) {
+        (Some(secret), None) => secret,
+        _ => {
+            return Err(status::Custom(
+                Status::BadRequest,
+                Json(ResponseError {
+                    error: "No pending two-factor enrollment".to_string(),
+                }),
+            ))
+        }
+    };
+    let mut conn = db_pool.acquire().await.map_err(db_error)?;
+    if !accept_code(&mut conn, user_id, &secret, &code.code)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(invalid_code());
+    }
+    drop(conn);
+
+    let recovery_codes = generate_recovery_codes();
+    store_recovery_codes(db_pool.inner(), user_id, &recovery_codes)
+        .await
+        .map_err(db_error)?;
+    sqlx::query!(
+        "UPDATE users SET totp_enabled_at = NOW() WHERE id = ?",
+        user_id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
+
+#[post("/2fa/disable", data = "<code>")]
+pub async fn disable_two_factor(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    client_ip: Option<IpAddr>,
+    code: Json<TwoFactorCode>,
+) -> Result<status::Custom<()>, SignInError> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let record = sqlx::query!(
+        "SELECT email, totp_secret FROM users WHERE id = ? AND totp_enabled_at IS NOT NULL",
+        user_id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_enabled)?;
+    let secret = record.totp_secret.ok_or_else(not_enabled)?;
+    let (email, ip) = throttle_keys(&record.email, client_ip);
+    ensure_not_locked_out(db_pool.inner(), &email, &ip).await?;
+    let mut conn = db_pool.acquire().await.map_err(db_error)?;
+    let accepted = verify_second_factor(&mut conn, user_id, &secret, &code.code)
+        .await
+        .map_err(db_error)?;
+    drop(conn);
+    if !accepted {
+        record_failed_sign_in(db_pool.inner(), &email, &ip)
+            .await
+            .map_err(db_error)?;
+        return Err(invalid_code().into());
+    }
+
+    sqlx::query!(
+        "UPDATE users SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL
+         WHERE id = ?",
+        user_id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = ?", user_id)
+        .execute(db_pool.inner())
+        .await
+        .map_err(db_error)?;
+
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+#[post("/2fa/recovery-codes", data = "<code>")]
+pub async fn regenerate_recovery_codes(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    client_ip: Option<IpAddr>,
+    code: Json<TwoFactorCode>,
+) -> Result<Json<RecoveryCodes>, SignInError> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let record = sqlx::query!(
+        "SELECT email, totp_secret FROM users WHERE id = ? AND totp_enabled_at IS NOT NULL",
+        user_id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(not_enabled)?;
+    let secret = record.totp_secret.ok_or_else(not_enabled)?;
+    let (email, ip) = throttle_keys(&record.email, client_ip);
+    ensure_not_locked_out(db_pool.inner(), &email, &ip).await?;
+    // only a real authenticator code may replace the recovery codes
+    let mut conn = db_pool.acquire().await.map_err(db_error)?;
+    let accepted = accept_code(&mut conn, user_id, &secret, &code.code)
+        .await
+        .map_err(db_error)?;
+    drop(conn);
+    if !accepted {
+        record_failed_sign_in(db_pool.inner(), &email, &ip)
+            .await
+            .map_err(db_error)?;
+        return Err(invalid_code().into());
+    }
+
+    let recovery_codes = generate_recovery_codes();
+    store_recovery_codes(db_pool.inner(), user_id, &recovery_codes)
+        .await
+        .map_err(db_error)?;
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
+
+// second step of sign in for users with 2FA enabled
+#[post("/2fa/verify", data = "<verification>")]
+pub async fn verify_two_factor(
+    db_pool: &rocket::State<Db>,
+    keyring: &rocket::State<Keyring>,
+    cookie: &CookieJar<'_>,
+    client_ip: Option<IpAddr>,
+    verification: Json<TwoFactorVerification>,
+) -> Result<Json<User>, SignInError> {
+    let invalid_challenge = || {
+        status::Custom(
+            Status::Unauthorized,
+            Json(ResponseError {
+                error: "Invalid or expired challenge".to_string(),
+            }),
+        )
+    };
+    let claims = verify_two_factor_challenge(keyring, &verification.challenge_token)
+        .map_err(|_| invalid_challenge())?
+        .claims;
+    let user_id = claims.sub.parse::<i64>().unwrap();
+    // a challenge is good for one sign in and a handful of codes
+    if !claim_challenge_attempt(db_pool.inner(), &claims.jti, user_id)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(invalid_challenge().into());
+    }
+
+    let record = sqlx::query!("SELECT * FROM users WHERE id = ?", user_id)
+        .fetch_one(db_pool.inner())
+        .await
+        .map_err(db_error)?;
+    let (email, ip) = throttle_keys(&record.email, client_ip);
+    ensure_not_locked_out(db_pool.inner(), &email, &ip).await?;
+    let secret = record.totp_secret.clone().ok_or_else(invalid_code)?;
+    // consuming first locks the challenge, a parallel request waits and then finds it used
+    // instead of burning a recovery code for nothing. a wrong code rolls the consumption back
+    let mut tx = db_pool.begin().await.map_err(db_error)?;
+    if !consume_challenge(&mut tx, &claims.jti)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(invalid_challenge().into());
+    }
+    if !verify_second_factor(&mut tx, user_id, &secret, &verification.code)
+        .await
+        .map_err(db_error)?
+    {
+        tx.rollback().await.map_err(db_error)?;
+        record_failed_sign_in(db_pool.inner(), &email, &ip)
+            .await
+            .map_err(db_error)?;
+        return Err(invalid_code().into());
+    }
+    tx.commit().await.map_err(db_error)?;
+    clear_failed_sign_ins(db_pool.inner(), &email)
+        .await
+        .map_err(db_error)?;
+
+    let user = User {
+        created_at: timestamp_to_datetime!(record).expect("faild to parse date"),
+        email: record.email,
+        password: None,
+        email_verified_at: timestamp_to_datetime!(record, email_verified_at),
+        avatar: avatar_urls(record.id as i64, record.avatar_version.as_deref()),
+        username: record.username,
+        id: record.id,
+        role: record.role,
+    };
+    start_session(db_pool, keyring, cookie, user.id as i64, &user.role).await?;
+    Ok(Json(user))
+}
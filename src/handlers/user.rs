@@ -0,0 +1,342 @@
+use blog_api::timestamp_to_datetime;
+use chrono::{DateTime, Utc};
+use rocket::{
+    http::Status,
+    response::{status, Redirect},
+    serde::json::Json,
+};
+
+use crate::{
+    auth::{
+        password::{hash_password, verify_password},
+        session::revoke_other_sessions,
+    },
+    avatar::avatar_urls,
+    db::Db,
+    guards::jwt_guard::JwtAuth,
+    handle::{
+        is_handle_available, normalize_handle, resolve_handle, validate_handle, HandleLookup,
+        HandleResponse, HANDLE_REDIRECT_DAYS,
+    },
+    models::{
+        error::{db_error, ResponseError},
+        user::{ChangeHandle, ChangePassword, Profile, PublicProfile, UpdateProfile},
+    },
+};
+
+const MAX_USERNAME_LENGTH: usize = 255;
+const MAX_DISPLAY_NAME_LENGTH: usize = 100;
+const MAX_BIO_LENGTH: usize = 2000;
+const MAX_WEBSITE_LENGTH: usize = 255;
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+fn bad_request(error: &str) -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::BadRequest,
+        Json(ResponseError {
+            error: error.to_string(),
+        }),
+    )
+}
+
+fn user_not_found() -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::NotFound,
+        Json(ResponseError {
+            error: "User not found".to_string(),
+        }),
+    )
+}
+
+// trims the value and turns an empty string into NULL
+fn optional_field(
+    value: &Option<String>,
+    current: Option<String>,
+    max_length: usize,
+    field: &str,
+) -> Result<Option<String>, status::Custom<Json<ResponseError>>> {
+    match value {
+        None => Ok(current),
+        Some(value) => {
+            let value = value.trim();
+            if value.chars().count() > max_length {
+                return Err(bad_request(&format!(
+                    "{} must be at most {} characters",
+                    field, max_length
+                )));
+            }
+            Ok((!value.is_empty()).then(|| value.to_string()))
+        }
+    }
+}
+
+pub async fn load_profile(
+    db_pool: &rocket::State<Db>,
+    user_id: i64,
+) -> Result<Profile, status::Custom<Json<ResponseError>>> {
+    let record = sqlx::query!(
+        r#"SELECT id, username, handle, email, display_name, bio, website, avatar_version, role, email_verified_at, created_at,
+           (SELECT COUNT(*) FROM follows WHERE followee_id = users.id) AS "followers_count!",
+           (SELECT COUNT(*) FROM follows WHERE follower_id = users.id) AS "following_count!"
+           FROM users WHERE id = ?"#,
+        user_id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(user_not_found)?;
+
+    Ok(Profile {
+        id: record.id,
+        username: record.username,
+        handle: record.handle,
+        email: record.email,
+        display_name: record.display_name,
+        bio: record.bio,
+        website: record.website,
+        avatar: avatar_urls(record.id as i64, record.avatar_version.as_deref()),
+        followers_count: record.followers_count,
+        following_count: record.following_count,
+        role: record.role,
+        email_verified_at: timestamp_to_datetime!(record, email_verified_at),
+        created_at: timestamp_to_datetime!(record).expect("faild to parse date"),
+    })
+}
+
+#[get("/me")]
+pub async fn get_me(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+) -> Result<Json<Profile>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    Ok(Json(load_profile(db_pool, user_id).await?))
+}
+
+#[patch("/me", data = "<changes>")]
+pub async fn update_me(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    changes: Json<UpdateProfile>,
+) -> Result<Json<Profile>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let profile = load_profile(db_pool, user_id).await?;
+
+    let username = match &changes.username {
+        None => profile.username,
+        Some(username) => {
+            let username = username.trim();
+            if username.is_empty() {
+                return Err(bad_request("Username must not be empty"));
+            }
+            if username.chars().count() > MAX_USERNAME_LENGTH {
+                return Err(bad_request(&format!(
+                    "Username must be at most {} characters",
+                    MAX_USERNAME_LENGTH
+                )));
+            }
+            username.to_string()
+        }
+    };
+    let display_name = optional_field(
+        &changes.display_name,
+        profile.display_name,
+        MAX_DISPLAY_NAME_LENGTH,
+        "Display name",
+    )?;
+    let bio = optional_field(&changes.bio, profile.bio, MAX_BIO_LENGTH, "Bio")?;
+    let website = optional_field(
+        &changes.website,
+        profile.website,
+        MAX_WEBSITE_LENGTH,
+        "Website",
+    )?;
+    // only links that are safe to render as an href
+    if let Some(website) = &website {
+        if !(website.starts_with("https://") || website.starts_with("http://")) {
+            return Err(bad_request("Website must be an http or https URL"));
+        }
+    }
+
+    sqlx::query!(
+        "UPDATE users SET username = ?, display_name = ?, bio = ?, website = ? WHERE id = ?",
+        username,
+        display_name,
+        bio,
+        website,
+        user_id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(load_profile(db_pool, user_id).await?))
+}
+
+#[put("/me/password", data = "<request>")]
+pub async fn change_password(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    request: Json<ChangePassword>,
+) -> Result<status::Custom<()>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    if request.new_password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(bad_request(&format!(
+            "Password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        )));
+    }
+
+    let password_hash = sqlx::query_scalar!("SELECT password FROM users WHERE id = ?", user_id)
+        .fetch_optional(db_pool.inner())
+        .await
+        .map_err(db_error)?
+        .ok_or_else(user_not_found)?;
+
+    let password_matches = password_hash.as_ref().is_some_and(|password_hash| {
+        verify_password(&request.current_password, password_hash).is_ok()
+    });
+    if !password_matches {
+        return Err(status::Custom(
+            Status::Forbidden,
+            Json(ResponseError {
+                error: "Current password is incorrect".to_string(),
+            }),
+        ));
+    }
+
+    sqlx::query!(
+        "UPDATE users SET password = ? WHERE id = ?",
+        hash_password(&request.new_password),
+        user_id
+    )
+    .execute(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    // every other device has to sign in with the new password
+    revoke_other_sessions(db_pool.inner(), user_id, user.claims.sid)
+        .await
+        .map_err(db_error)?;
+    Ok(status::Custom(Status::NoContent, ()))
+}
+
+// the old handle keeps redirecting for a while and stays reserved for its owner
+#[put("/me/handle", data = "<request>")]
+pub async fn change_handle(
+    db_pool: &rocket::State<Db>,
+    user: JwtAuth,
+    request: Json<ChangeHandle>,
+) -> Result<Json<Profile>, status::Custom<Json<ResponseError>>> {
+    let user_id = user.claims.sub.parse::<i64>().unwrap();
+    let handle = normalize_handle(&request.handle);
+    validate_handle(&handle).map_err(|error| bad_request(&error))?;
+
+    let handle_taken = || {
+        status::Custom(
+            Status::Conflict,
+            Json(ResponseError {
+                error: "Handle is already taken".to_string(),
+            }),
+        )
+    };
+    let mut tx = db_pool.begin().await.map_err(db_error)?;
+    let old_handle =
+        sqlx::query_scalar!("SELECT handle FROM users WHERE id = ? FOR UPDATE", user_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(db_error)?
+            .ok_or_else(user_not_found)?;
+    if old_handle == handle {
+        return Ok(Json(load_profile(db_pool, user_id).await?));
+    }
+    if !is_handle_available(&mut *tx, &handle, Some(user_id))
+        .await
+        .map_err(db_error)?
+    {
+        return Err(handle_taken());
+    }
+
+    // taking back one of our own old handles, or one whose grace period is over
+    sqlx::query!("DELETE FROM handle_redirects WHERE old_handle = ?", handle)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query!(
+        "INSERT INTO handle_redirects (old_handle, user_id, expires_at) VALUES (?, ?, NOW() + INTERVAL ? DAY)
+         ON DUPLICATE KEY UPDATE user_id = VALUES(user_id), expires_at = VALUES(expires_at)",
+        old_handle,
+        user_id,
+        HANDLE_REDIRECT_DAYS
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    sqlx::query!("UPDATE users SET handle = ? WHERE id = ?", handle, user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            // another sign up grabbed it between the check and the update
+            Some(e) if e.is_unique_violation() => handle_taken(),
+            _ => db_error(e),
+        })?;
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(Json(load_profile(db_pool, user_id).await?))
+}
+
+// public profile, looked up by numeric id or by handle
+#[get("/<id_or_handle>")]
+pub async fn get_user(
+    db_pool: &rocket::State<Db>,
+    id_or_handle: &str,
+) -> Result<HandleResponse<PublicProfile>, status::Custom<Json<ResponseError>>> {
+    let user_id = match id_or_handle.parse::<i64>() {
+        Ok(user_id) => user_id,
+        Err(_) => match resolve_handle(db_pool.inner(), id_or_handle)
+            .await
+            .map_err(db_error)?
+        {
+            Some(HandleLookup::Current(user_id)) => user_id,
+            Some(HandleLookup::Moved(handle)) => {
+                return Ok(HandleResponse::Moved(Redirect::moved(format!(
+                    "/users/{}",
+                    handle
+                ))))
+            }
+            None => return Err(user_not_found()),
+        },
+    };
+    Ok(HandleResponse::Found(Json(
+        load_public_profile(db_pool, user_id).await?,
+    )))
+}
+
+pub async fn load_public_profile(
+    db_pool: &rocket::State<Db>,
+    user_id: i64,
+) -> Result<PublicProfile, status::Custom<Json<ResponseError>>> {
+    let record = sqlx::query!(
+        r#"SELECT id, username, handle, display_name, bio, website, avatar_version, created_at,
+           (SELECT COUNT(*) FROM follows WHERE followee_id = users.id) AS "followers_count!",
+           (SELECT COUNT(*) FROM follows WHERE follower_id = users.id) AS "following_count!"
+           FROM users WHERE id = ?"#,
+        user_id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(user_not_found)?;
+
+    Ok(PublicProfile {
+        id: record.id,
+        username: record.username,
+        handle: record.handle,
+        display_name: record.display_name,
+        bio: record.bio,
+        website: record.website,
+        avatar: avatar_urls(record.id as i64, record.avatar_version.as_deref()),
+        followers_count: record.followers_count,
+        following_count: record.following_count,
+        created_at: timestamp_to_datetime!(record).expect("faild to parse date"),
+    })
+}
@@ -0,0 +1,196 @@
+use blog_api::timestamp_to_datetime;
+use chrono::{DateTime, Utc};
+use rocket::{
+    http::{uri::Origin, Status},
+    response::{status, Redirect},
+    serde::json::Json,
+};
+
+use crate::{
+    db::Db,
+    handle::{resolve_handle, AtHandle, HandleLookup, HandleResponse},
+    handlers::user::load_public_profile,
+    models::{
+        comment::Comment,
+        error::{db_error, ResponseError},
+        post::{post_from_row, Pagination, Post},
+        user::PublicProfile,
+        PagedResponse,
+    },
+    taxonomy::attach_tags,
+};
+
+// the user behind an @handle, or a redirect to the same path under their new handle
+async fn resolve(
+    db_pool: &rocket::State<Db>,
+    handle: &AtHandle,
+    origin: &Origin<'_>,
+) -> Result<Result<i64, Redirect>, status::Custom<Json<ResponseError>>> {
+    match resolve_handle(db_pool.inner(), &handle.0)
+        .await
+        .map_err(db_error)?
+    {
+        Some(HandleLookup::Current(user_id)) => Ok(Ok(user_id)),
+        Some(HandleLookup::Moved(new_handle)) => {
+            let rest = origin
+                .path()
+                .as_str()
+                .splitn(3, '/')
+                .nth(2)
+                .map_or(String::new(), |rest| format!("/{}", rest));
+            let query = origin
+                .query()
+                .map_or(String::new(), |query| format!("?{}", query));
+            Ok(Err(Redirect::moved(format!(
+                "/@{}{}{}",
+                new_handle, rest, query
+            ))))
+        }
+        None => Err(status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "User not found".to_string(),
+            }),
+        )),
+    }
+}
+
+#[get("/<handle>", rank = 2)]
+pub async fn get_profile_by_handle(
+    db_pool: &rocket::State<Db>,
+    origin: &Origin<'_>,
+    handle: AtHandle,
+) -> Result<HandleResponse<PublicProfile>, status::Custom<Json<ResponseError>>> {
+    let user_id = match resolve(db_pool, &handle, origin).await? {
+        Ok(user_id) => user_id,
+        Err(redirect) => return Ok(HandleResponse::Moved(redirect)),
+    };
+    Ok(HandleResponse::Found(Json(
+        load_public_profile(db_pool, user_id).await?,
+    )))
+}
+
+#[get("/<handle>/posts?<pagination..>", rank = 2)]
+pub async fn get_posts_by_handle(
+    db_pool: &rocket::State<Db>,
+    origin: &Origin<'_>,
+    handle: AtHandle,
+    pagination: Option<Pagination>,
+) -> Result<HandleResponse<PagedResponse<Post>>, status::Custom<Json<ResponseError>>> {
+    let author_id = match resolve(db_pool, &handle, origin).await? {
+        Ok(user_id) => user_id,
+        Err(redirect) => return Ok(HandleResponse::Moved(redirect)),
+    };
+    let page = pagination.as_ref().map_or(1, |p| p.page.unwrap_or(1)) as i64;
+    let size = pagination.as_ref().map_or(10, |p| p.size.unwrap_or(10)) as i64;
+    let size = size.max(1);
+    let offset = (page - 1) * size;
+
+    let query = sqlx::query!(
+        "SELECT * FROM posts WHERE author_id = ? AND status = 'published' AND deleted_at IS NULL
+         ORDER BY published_at DESC LIMIT ? OFFSET ?",
+        author_id,
+        size,
+        offset
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    let total_items = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM posts WHERE author_id = ? AND status = 'published' AND deleted_at IS NULL",
+        author_id
+    )
+    .fetch_one(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+    let total_pages = if total_items > 0 {
+        (total_items + size - 1) / size
+    } else {
+        0
+    };
+
+    let mut posts: Vec<Post> = query.iter().map(|row| post_from_row!(row)).collect();
+    attach_tags(db_pool.inner(), &mut posts)
+        .await
+        .map_err(db_error)?;
+
+    Ok(HandleResponse::Found(Json(PagedResponse {
+        current_page: page,
+        page_size: size,
+        total_items,
+        total_pages,
+        data: posts,
+    })))
+}
+
+#[get("/<handle>/posts/<id>", rank = 2)]
+pub async fn get_post_by_handle(
+    db_pool: &rocket::State<Db>,
+    origin: &Origin<'_>,
+    handle: AtHandle,
+    id: i64,
+) -> Result<HandleResponse<Post>, status::Custom<Json<ResponseError>>> {
+    let author_id = match resolve(db_pool, &handle, origin).await? {
+        Ok(user_id) => user_id,
+        Err(redirect) => return Ok(HandleResponse::Moved(redirect)),
+    };
+    let record = sqlx::query!(
+        "SELECT * FROM posts WHERE id = ? AND author_id = ? AND status = 'published'
+         AND deleted_at IS NULL",
+        id,
+        author_id
+    )
+    .fetch_optional(db_pool.inner())
+    .await
+    .map_err(db_error)?
+    .ok_or_else(|| {
+        status::Custom(
+            Status::NotFound,
+            Json(ResponseError {
+                error: "Post not found".to_string(),
+            }),
+        )
+    })?;
+
+    let mut post = post_from_row!(record);
+    attach_tags(db_pool.inner(), std::slice::from_mut(&mut post))
+        .await
+        .map_err(db_error)?;
+    Ok(HandleResponse::Found(Json(post)))
+}
+
+#[get("/<handle>/comments", rank = 2)]
+pub async fn get_comments_by_handle(
+    db_pool: &rocket::State<Db>,
+    origin: &Origin<'_>,
+    handle: AtHandle,
+) -> Result<HandleResponse<Vec<Comment>>, status::Custom<Json<ResponseError>>> {
+    let author_id = match resolve(db_pool, &handle, origin).await? {
+        Ok(user_id) => user_id,
+        Err(redirect) => return Ok(HandleResponse::Moved(redirect)),
+    };
+    let query = sqlx::query!(
+        "SELECT c.* FROM comments c JOIN posts p ON p.id = c.post_id
+         WHERE c.author_id = ? AND c.deleted_at IS NULL AND p.status = 'published'
+         AND p.deleted_at IS NULL ORDER BY c.created_at DESC",
+        author_id
+    )
+    .fetch_all(db_pool.inner())
+    .await
+    .map_err(db_error)?;
+
+    let comments: Vec<Comment> = query
+        .iter()
+        .map(|row| {
+            let created_at = timestamp_to_datetime!(row).expect("faild to parse datatime");
+            Comment {
+                id: row.id as i64,
+                author_id: row.author_id as i64,
+                post_id: row.post_id as i64,
+                body: row.body.clone(),
+                created_at,
+            }
+        })
+        .collect();
+    Ok(HandleResponse::Found(Json(comments)))
+}
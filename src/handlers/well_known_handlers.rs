@@ -0,0 +1,10 @@
+use jsonwebtoken::jwk::JwkSet;
+use rocket::serde::json::Json;
+
+use crate::auth::keys::Keyring;
+
+// public keys other services use to verify tokens issued by the blog
+#[get("/.well-known/jwks.json")]
+pub fn jwks(keyring: &rocket::State<Keyring>) -> Json<JwkSet> {
+    Json(keyring.jwks())
+}
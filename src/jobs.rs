@@ -0,0 +1,94 @@
+use std::{future::Future, pin::Pin, time::Duration};
+
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    Orbit, Rocket,
+};
+
+use crate::{
+    account::run_due_deletions, config::trash_retention_days, db::Db,
+    publishing::publish_due_posts, storage::Store, trash::purge_trash,
+};
+
+const ACCOUNT_DELETION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const SCHEDULED_PUBLISH_INTERVAL: Duration = Duration::from_secs(60);
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// what a job gets to work with on every run
+#[derive(Clone)]
+pub struct JobContext {
+    pub db_pool: Db,
+    pub storage: Store,
+}
+
+type JobRun = Pin<Box<dyn Future<Output = Result<u64, sqlx::Error>> + Send>>;
+
+// runs `run` every `interval` once the server is up. jobs that work through a queue claim
+// one row per transaction with FOR UPDATE SKIP LOCKED, so several instances can share the
+// queue without doing a row twice
+pub struct PeriodicJob {
+    name: &'static str,
+    interval: Duration,
+    run: fn(JobContext) -> JobRun,
+}
+
+impl PeriodicJob {
+    // carries out account deletions once their cool-off has passed
+    pub fn account_deletion() -> Self {
+        PeriodicJob {
+            name: "Account deletion job",
+            interval: ACCOUNT_DELETION_INTERVAL,
+            run: |ctx| Box::pin(async move { run_due_deletions(&ctx.db_pool, &ctx.storage).await }),
+        }
+    }
+
+    // publishes scheduled posts once their publish_at has passed
+    pub fn scheduled_publish() -> Self {
+        PeriodicJob {
+            name: "Scheduled publish job",
+            interval: SCHEDULED_PUBLISH_INTERVAL,
+            run: |ctx| Box::pin(async move { publish_due_posts(&ctx.db_pool).await }),
+        }
+    }
+
+    // removes posts and comments for good once their time in the trash is up
+    pub fn trash_purge() -> Self {
+        PeriodicJob {
+            name: "Trash purge job",
+            interval: TRASH_PURGE_INTERVAL,
+            run: |ctx| {
+                Box::pin(async move { purge_trash(&ctx.db_pool, trash_retention_days()).await })
+            },
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for PeriodicJob {
+    fn info(&self) -> Info {
+        Info {
+            name: self.name,
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let ctx = JobContext {
+            db_pool: rocket
+                .state::<Db>()
+                .expect("database pool is managed")
+                .clone(),
+            storage: rocket.state::<Store>().expect("storage is managed").clone(),
+        };
+        let (name, interval, run) = (self.name, self.interval, self.run);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = run(ctx.clone()).await {
+                    error!("{} failed: {}", name, e);
+                }
+            }
+        });
+    }
+}
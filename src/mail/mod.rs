@@ -0,0 +1,41 @@
+use std::{env, fmt};
+
+pub mod outbox;
+pub mod smtp;
+
+use outbox::OutboxMailer;
+use smtp::SmtpMailer;
+
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct MailError(pub String);
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to send mail: {}", self.0)
+    }
+}
+
+#[rocket::async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), MailError>;
+}
+
+pub type Mail = Box<dyn Mailer>;
+
+// MAIL_TRANSPORT picks the implementation, "outbox" keeps everything on disk
+pub fn mailer_from_env() -> Mail {
+    match env::var("MAIL_TRANSPORT")
+        .unwrap_or_else(|_| "outbox".to_string())
+        .as_str()
+    {
+        "smtp" => Box::new(SmtpMailer::from_env()),
+        "outbox" => Box::new(OutboxMailer::from_env()),
+        other => panic!("unknown MAIL_TRANSPORT `{}`", other),
+    }
+}
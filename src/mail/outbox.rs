@@ -0,0 +1,38 @@
+use std::{env, path::PathBuf};
+
+use chrono::Utc;
+
+use super::{Email, MailError, Mailer};
+
+// writes every mail to a directory instead of sending it, for local development and tests
+pub struct OutboxMailer {
+    dir: PathBuf,
+}
+
+impl OutboxMailer {
+    pub fn from_env() -> Self {
+        let dir = env::var("MAIL_OUTBOX_DIR").unwrap_or_else(|_| "outbox".to_string());
+        OutboxMailer { dir: dir.into() }
+    }
+}
+
+#[rocket::async_trait]
+impl Mailer for OutboxMailer {
+    async fn send(&self, email: &Email) -> Result<(), MailError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| MailError(e.to_string()))?;
+        let file_name = format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+            email.to.replace(['/', '\\'], "_")
+        );
+        let content = format!(
+            "To: {}\nSubject: {}\n\n{}\n",
+            email.to, email.subject, email.body
+        );
+        tokio::fs::write(self.dir.join(file_name), content)
+            .await
+            .map_err(|e| MailError(e.to_string()))
+    }
+}
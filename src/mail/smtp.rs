@@ -0,0 +1,51 @@
+use std::env;
+
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+use super::{Email, MailError, Mailer};
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn from_env() -> Self {
+        let host = env::var("SMTP_HOST").expect("error please provide SMTP_HOST in .env file");
+        let username =
+            env::var("SMTP_USERNAME").expect("error please provide SMTP_USERNAME in .env file");
+        let password =
+            env::var("SMTP_PASSWORD").expect("error please provide SMTP_PASSWORD in .env file");
+        let from = env::var("MAIL_FROM")
+            .expect("error please provide MAIL_FROM in .env file")
+            .parse()
+            .expect("MAIL_FROM is not a valid mailbox");
+
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::relay(&host)
+            .expect("faild to build smtp transport")
+            .credentials(Credentials::new(username, password))
+            .build();
+        SmtpMailer { transport, from }
+    }
+}
+
+#[rocket::async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> Result<(), MailError> {
+        let to: Mailbox = email.to.parse().map_err(|e| MailError(format!("{}", e)))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(email.subject.clone())
+            .body(email.body.clone())
+            .map_err(|e| MailError(e.to_string()))?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| MailError(e.to_string()))?;
+        Ok(())
+    }
+}
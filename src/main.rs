@@ -1,23 +1,73 @@
 #[macro_use]
 extern crate rocket;
 
+mod account;
 mod auth;
+mod avatar;
+mod config;
 mod db;
 mod guards;
+mod handle;
 mod handlers;
+mod jobs;
+mod mail;
+mod markdown;
 mod models;
+mod publishing;
+mod revisions;
 mod routes;
+mod search;
+mod slug;
+mod storage;
+mod taxonomy;
+mod trash;
+use auth::keys::Keyring;
+use avatar::MAX_AVATAR_BYTES;
+use blog_api::oidc::Oidc;
+use config::{trusted_ip_header, AuthConfig};
 use db::{db_conncetion, Db};
 use dotenv::dotenv;
-use rocket::{Build, Rocket};
+use jobs::PeriodicJob;
+use mail::mailer_from_env;
+use rocket::{Build, Config, Rocket};
+use storage::storage_from_env;
 
 #[launch]
 async fn rocket() -> Rocket<Build> {
     dotenv().ok();
     let db_pool: Db = db_conncetion().await;
-    rocket::build()
+    // room for avatar uploads, the defaults stop at 1 MiB per file
+    let figment = Config::figment()
+        .merge(("limits.file", MAX_AVATAR_BYTES))
+        .merge(("limits.data-form", MAX_AVATAR_BYTES + 1024 * 1024));
+    // client ips feed the sign in lockout, only read them from a header behind a proxy
+    let figment = match trusted_ip_header() {
+        Some(header) => figment.merge(("ip_header", header)),
+        None => figment.merge(("ip_header", false)),
+    };
+    rocket::custom(figment)
         .manage(db_pool)
+        .manage(AuthConfig::from_env())
+        .manage(Keyring::from_env())
+        .manage(mailer_from_env())
+        .manage(Oidc::from_env())
+        .manage(storage_from_env())
+        .attach(PeriodicJob::account_deletion())
+        .attach(PeriodicJob::scheduled_publish())
+        .attach(PeriodicJob::trash_purge())
         .mount("/", routes::posts_routes::posts_routes())
         .mount("/", routes::comment_routes::comment_routes())
+        .mount("/", routes::api_key_routes::api_key_routes())
+        .mount("/", routes::feed_routes::feed_routes())
+        .mount("/", routes::taxonomy_routes::taxonomy_routes())
+        .mount("/", routes::search_routes::search_routes())
+        .mount("/", routes::revision_routes::revision_routes())
+        .mount("/", routes::trash_routes::trash_routes())
         .mount("/auth", routes::auth_routes::get_auth_routes())
+        .mount("/users", routes::user_routes::user_routes())
+        .mount("/admin", routes::admin_routes::admin_routes())
+        .mount("/", routes::well_known_routes::well_known_routes())
+        .mount("/", routes::avatar_routes::avatar_routes())
+        .mount("/", routes::vanity_routes::vanity_routes())
+        .register("/", routes::catchers::get_catchers())
 }
@@ -0,0 +1,195 @@
+use std::{borrow::Cow, collections::HashSet, sync::LazyLock};
+
+use ammonia::Builder;
+use pulldown_cmark::{html, Options, Parser};
+
+// CommonMark plus the GitHub extensions authors expect
+fn options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+}
+
+// ids are prefixed so a post cannot clobber ids the page itself relies on
+const ID_PREFIX: &str = "user-content-";
+
+// keeps in-page links such as footnote references pointing at the prefixed ids
+fn prefix_fragment_links<'u>(
+    element: &str,
+    attribute: &str,
+    value: &'u str,
+) -> Option<Cow<'u, str>> {
+    match (element, attribute, value.strip_prefix('#')) {
+        ("a", "href", Some(fragment)) => Some(format!("#{}{}", ID_PREFIX, fragment).into()),
+        _ => Some(value.into()),
+    }
+}
+
+// ammonia's defaults already drop scripts, styles, event handlers and javascript: urls,
+// on top of them we keep what the markdown renderer itself produces
+static SANITIZER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut builder = Builder::default();
+    builder
+        .add_tags(["input"])
+        // task list checkboxes, an input of any other type is turned into one
+        .add_tag_attributes("input", ["checked", "disabled"])
+        .set_tag_attribute_value("input", "type", "checkbox")
+        .add_tag_attributes("code", ["class"])
+        .add_tag_attributes("span", ["class"])
+        .add_tag_attributes("pre", ["class"])
+        .add_tag_attributes("sup", ["class", "id"])
+        .add_tag_attributes("div", ["class", "id"])
+        .add_tag_attributes("th", ["style"])
+        .add_tag_attributes("td", ["style"])
+        .filter_style_properties(HashSet::from(["text-align"]))
+        .id_prefix(Some(ID_PREFIX))
+        .attribute_filter(prefix_fragment_links);
+    builder
+});
+
+// markdown source to sanitised html, safe to embed in a page as is
+pub fn render_markdown(source: &str) -> String {
+    let parser = Parser::new_ext(source, options());
+    let mut unsafe_html = String::new();
+    #[cfg(feature = "syntax-highlighting")]
+    html::push_html(&mut unsafe_html, highlight::highlight_code_blocks(parser));
+    #[cfg(not(feature = "syntax-highlighting"))]
+    html::push_html(&mut unsafe_html, parser);
+    SANITIZER.clean(&unsafe_html).to_string()
+}
+
+#[cfg(feature = "syntax-highlighting")]
+mod highlight {
+    use std::sync::LazyLock;
+
+    use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag, TagEnd};
+    use syntect::{
+        html::{ClassStyle, ClassedHTMLGenerator},
+        parsing::SyntaxSet,
+        util::LinesWithEndings,
+    };
+
+    static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+
+    // spans carry `hl-` prefixed classes, the page supplies the theme stylesheet
+    fn highlight(language: &str, code: &str) -> Option<String> {
+        let syntax = SYNTAXES.find_syntax_by_token(language)?;
+        let mut generator = ClassedHTMLGenerator::new_with_class_style(
+            syntax,
+            &SYNTAXES,
+            ClassStyle::SpacedPrefixed { prefix: "hl-" },
+        );
+        for line in LinesWithEndings::from(code) {
+            generator
+                .parse_html_for_line_which_includes_newline(line)
+                .ok()?;
+        }
+        Some(generator.finalize())
+    }
+
+    // replaces fenced code blocks in a known language with highlighted html
+    pub fn highlight_code_blocks<'a>(
+        events: impl Iterator<Item = Event<'a>>,
+    ) -> impl Iterator<Item = Event<'a>> {
+        let mut language: Option<CowStr<'a>> = None;
+        let mut code = String::new();
+        events.filter_map(move |event| match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) if !lang.is_empty() => {
+                language = Some(lang);
+                code.clear();
+                None
+            }
+            Event::Text(text) if language.is_some() => {
+                code.push_str(&text);
+                None
+            }
+            Event::End(TagEnd::CodeBlock) => match language.take() {
+                Some(lang) => {
+                    let body = highlight(&lang, &code).unwrap_or_else(|| escape_html(&code));
+                    Some(Event::Html(
+                        format!(
+                            "<pre><code class=\"language-{}\">{}</code></pre>\n",
+                            escape_html(&lang),
+                            body
+                        )
+                        .into(),
+                    ))
+                }
+                None => Some(Event::End(TagEnd::CodeBlock)),
+            },
+            event => Some(event),
+        })
+    }
+
+    fn escape_html(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_markdown;
+
+    #[test]
+    fn drops_scripts() {
+        let html = render_markdown("hello <script>alert(1)</script>");
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("alert(1)"));
+    }
+
+    #[test]
+    fn drops_event_handlers() {
+        let html = render_markdown("<img src=\"cat.png\" onerror=\"alert(1)\">");
+        assert!(html.contains("<img src=\"cat.png\">"));
+        assert!(!html.contains("onerror"));
+    }
+
+    #[test]
+    fn drops_javascript_links() {
+        for source in [
+            "[click](javascript:alert(1))",
+            "<a href=\"javascript:alert(1)\">click</a>",
+        ] {
+            let html = render_markdown(source);
+            assert!(!html.contains("javascript:"), "{}", html);
+            assert!(html.contains("click"));
+        }
+    }
+
+    #[test]
+    fn prefixes_ids_and_fragment_links() {
+        let html = render_markdown("<div id=\"top\">x</div>\n\n[back](#top)");
+        assert!(html.contains("id=\"user-content-top\""), "{}", html);
+        assert!(html.contains("href=\"#user-content-top\""), "{}", html);
+    }
+
+    #[test]
+    fn prefixes_footnotes() {
+        let html = render_markdown("text[^1]\n\n[^1]: note");
+        assert!(html.contains("href=\"#user-content-1\""), "{}", html);
+        assert!(html.contains("id=\"user-content-1\""), "{}", html);
+    }
+
+    #[test]
+    fn keeps_task_list_checkboxes() {
+        let html = render_markdown("- [x] done\n- [ ] todo");
+        assert!(html.contains("type=\"checkbox\""), "{}", html);
+        assert!(html.contains("checked"), "{}", html);
+        assert!(html.contains("disabled"), "{}", html);
+    }
+
+    #[test]
+    fn inputs_only_keep_checkbox_attributes() {
+        let html = render_markdown(
+            "<input type=\"text\" name=\"password\" value=\"x\" autofocus onfocus=\"alert(1)\">",
+        );
+        assert!(html.contains("type=\"checkbox\""), "{}", html);
+        for attribute in ["text", "name", "value", "autofocus", "onfocus"] {
+            assert!(!html.contains(attribute), "{}", html);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use rocket::{http::Header, serde::json::Json};
+use serde::{Deserialize, Serialize};
+
+use crate::account::DeletionMode;
+
+use super::{api_key::ApiKey, comment::Comment, post::Post, user::Profile};
+
+#[derive(Serialize)]
+pub struct LinkedIdentity {
+    pub provider: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// everything we hold about a user, secrets and hashes left out
+#[derive(Serialize)]
+pub struct AccountExport {
+    pub exported_at: DateTime<Utc>,
+    pub profile: Profile,
+    pub posts: Vec<Post>,
+    pub comments: Vec<Comment>,
+    pub api_keys: Vec<ApiKey>,
+    pub identities: Vec<LinkedIdentity>,
+}
+
+// served as a file download rather than shown inline
+#[derive(Responder)]
+pub struct AccountExportDownload {
+    inner: Json<AccountExport>,
+    disposition: Header<'static>,
+}
+
+impl AccountExportDownload {
+    pub fn new(export: AccountExport) -> Self {
+        let filename = format!("account-{}-export.json", export.profile.id);
+        AccountExportDownload {
+            inner: Json(export),
+            disposition: Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeletionRequest {
+    pub password: String,
+    pub mode: DeletionMode,
+}
+
+#[derive(Serialize)]
+pub struct ScheduledDeletion {
+    pub mode: DeletionMode,
+    pub requested_at: DateTime<Utc>,
+    pub scheduled_for: DateTime<Utc>,
+}
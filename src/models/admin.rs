@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// full account view for user management, includes what public profiles hide
+#[derive(Serialize)]
+pub struct AdminUser {
+    pub id: i32,
+    pub username: String,
+    pub handle: String,
+    pub email: String,
+    pub role: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub suspension_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(FromForm)]
+pub struct UserSearch {
+    pub q: Option<String>,
+    pub page: Option<usize>,
+    pub size: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct ChangeRole {
+    pub role: String,
+}
+
+// leaving out expires_in_days bans the user until lifted
+#[derive(Deserialize)]
+pub struct Suspension {
+    pub reason: String,
+    pub expires_in_days: Option<i64>,
+}
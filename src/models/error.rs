@@ -1,6 +1,90 @@
+use rocket::{
+    http::{Header, Status},
+    response::status,
+    serde::json::Json,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize)]
 pub struct ResponseError {
     pub error: String,
 }
+
+// the details of a failed query are not for the client
+pub fn db_error(_: sqlx::Error) -> status::Custom<Json<ResponseError>> {
+    status::Custom(
+        Status::InternalServerError,
+        Json(ResponseError {
+            error: "Database Error".to_string(),
+        }),
+    )
+}
+
+// reason an authentication guard rejected the request
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthError {
+    Missing,
+    Malformed,
+    Expired,
+    BadSignature,
+    Revoked,
+    InvalidApiKey,
+    InsufficientScope,
+    EmailNotVerified,
+    MissingPermission,
+    Suspended,
+}
+
+impl AuthError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            AuthError::Missing => "No access token provided",
+            AuthError::Malformed => "Access token is malformed",
+            AuthError::Expired => "Access token has expired",
+            AuthError::BadSignature => "Access token signature is invalid",
+            AuthError::Revoked => "Session has been revoked",
+            AuthError::InvalidApiKey => "API key is invalid, expired or revoked",
+            AuthError::InsufficientScope => "API key is missing the required scope",
+            AuthError::EmailNotVerified => "Email address has not been verified",
+            AuthError::MissingPermission => "Your role does not allow this action",
+            AuthError::Suspended => "This account is suspended",
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct AuthResponseError {
+    pub error: String,
+    pub reason: AuthError,
+}
+
+#[derive(Responder)]
+#[response(status = 429)]
+pub struct TooManyRequests {
+    inner: Json<ResponseError>,
+    retry_after: Header<'static>,
+}
+
+impl TooManyRequests {
+    pub fn new(error: &str, retry_after_seconds: i64) -> Self {
+        TooManyRequests {
+            inner: Json(ResponseError {
+                error: error.to_string(),
+            }),
+            retry_after: Header::new("Retry-After", retry_after_seconds.to_string()),
+        }
+    }
+}
+
+#[derive(Responder)]
+pub enum SignInError {
+    TooManyRequests(TooManyRequests),
+    Failed(status::Custom<Json<ResponseError>>),
+}
+
+impl From<status::Custom<Json<ResponseError>>> for SignInError {
+    fn from(error: status::Custom<Json<ResponseError>>) -> Self {
+        SignInError::Failed(error)
+    }
+}
@@ -1,9 +1,24 @@
 use serde::Serialize;
 
+pub mod account;
+pub mod admin;
+pub mod api_key;
 pub mod comment;
 pub mod error;
 pub mod post;
+pub mod revision;
+pub mod search;
+pub mod taxonomy;
+pub mod trash;
+pub mod two_factor;
 pub mod user;
+// for lists that grow at the top, pass next_cursor back to get the following page
+#[derive(Serialize)]
+pub struct CursorResponse<T> {
+    pub data: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct PagedResponse<T> {
     pub data: Vec<T>,
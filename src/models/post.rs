@@ -1,12 +1,66 @@
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::prelude::FromRow;
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum PostStatus {
+    // only visible to the author
+    Draft,
+    Published,
+    // waiting for `publish_at`, then published by the scheduler
+    Scheduled,
+    // taken out of the public listings but kept
+    Archived,
+}
+
+impl PostStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostStatus::Draft => "draft",
+            PostStatus::Published => "published",
+            PostStatus::Scheduled => "scheduled",
+            PostStatus::Archived => "archived",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        match status {
+            "draft" => Some(PostStatus::Draft),
+            "published" => Some(PostStatus::Published),
+            "scheduled" => Some(PostStatus::Scheduled),
+            "archived" => Some(PostStatus::Archived),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<String> for PostStatus {
+    type Error = String;
+
+    fn try_from(status: String) -> Result<Self, Self::Error> {
+        PostStatus::parse(&status).ok_or_else(|| format!("unknown post status `{}`", status))
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 
 pub struct NewPost {
     pub title: String,
     pub body: String,
+    // new posts start as drafts unless asked otherwise
+    pub status: Option<PostStatus>,
+    pub tags: Option<Vec<String>>,
+    pub category_id: Option<i64>,
+}
+
+// tells a missing field (None) apart from an explicit null (Some(None))
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 #[derive(Serialize, Deserialize)]
@@ -14,6 +68,11 @@ pub struct NewPost {
 pub struct UpdatedPost {
     pub title: Option<String>,
     pub body: Option<String>,
+    // replaces the post's tags when present
+    pub tags: Option<Vec<String>>,
+    // null removes the post from its category
+    #[serde(default, deserialize_with = "double_option")]
+    pub category_id: Option<Option<i64>>,
 }
 
 #[derive(FromRow, Serialize, Deserialize)]
@@ -22,8 +81,59 @@ pub struct Post {
     pub id: i32,
     pub author_id: i32,
     pub title: String,
+    pub slug: String,
+    // markdown source
     pub body: String,
+    // sanitised html rendered from `body`
+    #[sqlx(skip)]
+    pub body_html: String,
+    #[sqlx(try_from = "String")]
+    pub status: PostStatus,
     pub created_at: DateTime<Utc>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub publish_at: Option<DateTime<Utc>>,
+    pub category_id: Option<i32>,
+    #[sqlx(skip)]
+    pub tags: Vec<String>,
+}
+
+// builds a Post from a `SELECT * FROM posts` row of `sqlx::query!`, tags are left empty
+// for `attach_tags`
+macro_rules! post_from_row {
+    ($row:expr) => {{
+        use ::chrono::{DateTime, Utc};
+        let row = &$row;
+        $crate::models::post::Post {
+            id: row.id,
+            author_id: row.author_id,
+            title: row.title.clone(),
+            slug: row.slug.clone(),
+            body: row.body.clone(),
+            body_html: row
+                .body_html
+                .clone()
+                .unwrap_or_else(|| $crate::markdown::render_markdown(&row.body)),
+            status: $crate::models::post::PostStatus::parse(&row.status)
+                .unwrap_or($crate::models::post::PostStatus::Draft),
+            created_at: ::blog_api::timestamp_to_datetime!(row).expect("Failed to parse date"),
+            published_at: ::blog_api::timestamp_to_datetime!(row, published_at),
+            publish_at: ::blog_api::timestamp_to_datetime!(row, publish_at),
+            category_id: row.category_id,
+            tags: Vec::new(),
+        }
+    }};
+}
+pub(crate) use post_from_row;
+
+#[derive(Serialize, Deserialize)]
+pub struct SchedulePost {
+    pub publish_at: DateTime<Utc>,
+}
+
+#[derive(FromForm)]
+pub struct CursorPagination {
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
 }
 
 #[derive(FromForm, Deserialize)]
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+// a saved version of a post's title and body
+#[derive(Serialize)]
+pub struct Revision {
+    pub id: i32,
+    pub post_id: i32,
+    // None once the editor's account is gone
+    pub editor_id: Option<i32>,
+    pub title: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct RevisionSummary {
+    pub id: i32,
+    pub editor_id: Option<i32>,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(FromFormField, Clone, Copy)]
+pub enum DiffMode {
+    Line,
+    Word,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Serialize)]
+pub struct DiffChunk {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+// what changed going from revision `from` to revision `to`
+#[derive(Serialize)]
+pub struct RevisionDiff {
+    pub from: i64,
+    pub to: i64,
+    pub title: Vec<DiffChunk>,
+    pub body: Vec<DiffChunk>,
+}
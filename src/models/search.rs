@@ -0,0 +1,22 @@
+use serde::Serialize;
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchHitKind {
+    Post,
+    Comment,
+}
+
+#[derive(Serialize)]
+pub struct SearchHit {
+    pub kind: SearchHitKind,
+    // the post or comment id
+    pub id: i32,
+    // the post the hit belongs to, the same as `id` for posts
+    pub post_id: i32,
+    pub post_title: String,
+    pub post_slug: String,
+    // html escaped, matches are wrapped in <mark>
+    pub snippet: String,
+    pub score: f64,
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+pub struct TagCount {
+    pub name: String,
+    // published posts only
+    pub post_count: i64,
+}
+
+#[derive(Deserialize)]
+pub struct RenameTag {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct MergeTag {
+    // the tag that takes over the posts, the merged one is removed
+    pub into: String,
+}
+
+#[derive(Serialize)]
+pub struct Category {
+    pub id: i32,
+    pub name: String,
+    pub slug: String,
+    pub parent_id: Option<i32>,
+    pub children: Vec<Category>,
+}
+
+#[derive(Deserialize)]
+pub struct NewCategory {
+    pub name: String,
+    pub parent_id: Option<i64>,
+}
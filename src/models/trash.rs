@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::post::PostStatus;
+
+#[derive(Serialize)]
+pub struct TrashedPost {
+    pub id: i32,
+    pub author_id: i32,
+    pub title: String,
+    pub slug: String,
+    // the status it goes back to when restored
+    pub status: PostStatus,
+    pub deleted_at: DateTime<Utc>,
+    // when the retention job removes it for good
+    pub purge_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct TrashedComment {
+    pub id: i32,
+    pub post_id: i32,
+    pub author_id: i32,
+    pub body: String,
+    pub deleted_at: DateTime<Utc>,
+    pub purge_at: DateTime<Utc>,
+}
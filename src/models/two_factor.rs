@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct TwoFactorCode {
+    pub code: String,
+}
+
+#[derive(Serialize)]
+pub struct TwoFactorSetup {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+// shown once, only hashes are stored
+#[derive(Serialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct TwoFactorChallenge {
+    pub two_factor_required: bool,
+    pub challenge_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorVerification {
+    pub challenge_token: String,
+    pub code: String,
+}
@@ -1,11 +1,19 @@
 use chrono::{DateTime, Utc};
+use rocket::{
+    fs::TempFile,
+    http::{ContentType, Header},
+};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
+use super::two_factor::TwoFactorChallenge;
+
 #[derive(Deserialize, Serialize)]
 
 pub struct NewUser {
     pub username: String,
+    // generated from the username when left out
+    pub handle: Option<String>,
     pub email: String,
     pub password: String,
 }
@@ -15,13 +23,127 @@ pub struct User {
     pub id: i32,
     pub username: String,
     pub email: String,
+    #[serde(skip_serializing)]
     pub password: Option<String>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    #[sqlx(skip)]
+    pub avatar: Option<AvatarUrls>,
     pub created_at: DateTime<Utc>,
     pub role: String,
 }
 
+// thumbnail urls, one per generated size
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AvatarUrls {
+    pub small: String,
+    pub medium: String,
+    pub large: String,
+}
+
+#[derive(FromForm)]
+pub struct AvatarUpload<'r> {
+    pub avatar: TempFile<'r>,
+}
+
+// versioned urls never change content, so caches may keep them for good
+#[derive(Responder)]
+pub struct AvatarImage {
+    inner: (ContentType, Vec<u8>),
+    cache_control: Header<'static>,
+}
+
+impl AvatarImage {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        AvatarImage {
+            inner: (ContentType::new("image", "webp"), bytes),
+            cache_control: Header::new("Cache-Control", "public, max-age=31536000, immutable"),
+        }
+    }
+}
+
+// what the signed in user sees about themselves
+#[derive(Serialize)]
+pub struct Profile {
+    pub id: i32,
+    pub username: String,
+    pub handle: String,
+    pub email: String,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub avatar: Option<AvatarUrls>,
+    pub followers_count: i64,
+    pub following_count: i64,
+    pub role: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// what everyone else sees, never add email or password here
+#[derive(Serialize)]
+pub struct PublicProfile {
+    pub id: i32,
+    pub username: String,
+    pub handle: String,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub avatar: Option<AvatarUrls>,
+    pub followers_count: i64,
+    pub following_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+// fields left out stay as they are, an empty string clears an optional field
+#[derive(Deserialize)]
+pub struct UpdateProfile {
+    pub username: Option<String>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+}
+
+// an entry in the caller's block or mute list
+#[derive(Serialize)]
+pub struct RelatedUser {
+    pub id: i32,
+    pub username: String,
+    pub handle: String,
+    pub since: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct ChangeHandle {
+    pub handle: String,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePassword {
+    pub current_password: String,
+    pub new_password: String,
+}
+
 #[derive(Deserialize)]
 pub struct UserCredential {
     pub email: String,
     pub password: String,
 }
+
+#[derive(Deserialize)]
+pub struct ForgotPassword {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPassword {
+    pub token: String,
+    pub password: String,
+}
+
+// sign in either completes or asks for the second factor
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum SignInResponse {
+    User(User),
+    TwoFactorChallenge(TwoFactorChallenge),
+}
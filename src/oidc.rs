@@ -0,0 +1,243 @@
+use std::{collections::HashMap, env, fmt};
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+pub struct OidcProvider {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+}
+
+// the subset of the discovery document the login flow needs
+#[derive(Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub preferred_username: Option<String>,
+    pub nonce: Option<String>,
+}
+
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+#[derive(Debug)]
+pub struct OidcError(pub String);
+
+impl fmt::Display for OidcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "oidc error: {}", self.0)
+    }
+}
+
+impl From<reqwest::Error> for OidcError {
+    fn from(e: reqwest::Error) -> Self {
+        OidcError(e.to_string())
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for OidcError {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        OidcError(e.to_string())
+    }
+}
+
+// issuers are compared without a trailing slash, `https://id.example.com/` is the same issuer
+fn normalize_issuer(issuer: &str) -> &str {
+    issuer.trim_end_matches('/')
+}
+
+pub fn generate_pkce() -> Pkce {
+    // 32 random bytes, hex encoded, well within the 43 to 128 characters RFC 7636 asks for
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let verifier: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+    Pkce {
+        verifier,
+        challenge,
+    }
+}
+
+pub struct Oidc {
+    pub providers: HashMap<String, OidcProvider>,
+    http: reqwest::Client,
+}
+
+impl Oidc {
+    pub fn new(providers: HashMap<String, OidcProvider>) -> Self {
+        Oidc {
+            providers,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    // OIDC_PROVIDERS=sso,gitlab with OIDC_SSO_ISSUER, OIDC_SSO_CLIENT_ID and
+    // OIDC_SSO_CLIENT_SECRET (optional for public clients) per provider
+    pub fn from_env() -> Self {
+        let providers = env::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let var = |key: &str| env::var(format!("OIDC_{}_{}", name.to_uppercase(), key));
+                let provider = OidcProvider {
+                    issuer: var("ISSUER")
+                        .unwrap_or_else(|_| panic!("missing issuer for oidc provider `{}`", name)),
+                    client_id: var("CLIENT_ID").unwrap_or_else(|_| {
+                        panic!("missing client id for oidc provider `{}`", name)
+                    }),
+                    client_secret: var("CLIENT_SECRET").ok(),
+                };
+                (name.to_string(), provider)
+            })
+            .collect();
+        Oidc::new(providers)
+    }
+
+    // the document must name the configured issuer, otherwise whoever serves it could
+    // vouch for tokens of another issuer
+    pub async fn discover(&self, provider: &OidcProvider) -> Result<ProviderMetadata, OidcError> {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            normalize_issuer(&provider.issuer)
+        );
+        let metadata = self
+            .http
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<ProviderMetadata>()
+            .await?;
+        if normalize_issuer(&metadata.issuer) != normalize_issuer(&provider.issuer) {
+            return Err(OidcError(format!(
+                "discovery document names issuer `{}`, expected `{}`",
+                metadata.issuer, provider.issuer
+            )));
+        }
+        Ok(metadata)
+    }
+
+    pub fn authorization_url(
+        &self,
+        metadata: &ProviderMetadata,
+        provider: &OidcProvider,
+        redirect_uri: &str,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<String, OidcError> {
+        let mut url =
+            Url::parse(&metadata.authorization_endpoint).map_err(|e| OidcError(e.to_string()))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &provider.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("scope", "openid email profile")
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", "S256");
+        Ok(url.to_string())
+    }
+
+    // trade the authorization code for the raw id token
+    pub async fn exchange_code(
+        &self,
+        metadata: &ProviderMetadata,
+        provider: &OidcProvider,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+    ) -> Result<String, OidcError> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("client_id", provider.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(secret) = &provider.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+        let response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<TokenResponse>()
+            .await?;
+        Ok(response.id_token)
+    }
+
+    // check signature against the provider's JWKS, then issuer, audience, expiry and nonce
+    pub async fn validate_id_token(
+        &self,
+        metadata: &ProviderMetadata,
+        provider: &OidcProvider,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let header = decode_header(id_token)?;
+        // never accept a symmetric algorithm, the JWKS only holds public keys
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(OidcError("unsupported id token algorithm".to_string()));
+        }
+
+        let jwks = self
+            .http
+            .get(&metadata.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<JwkSet>()
+            .await?;
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None => jwks.keys.first(),
+        }
+        .ok_or_else(|| OidcError("no matching key in provider JWKS".to_string()))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&provider.client_id]);
+        // `iss` is checked against the configured issuer, never against what the provider
+        // claims about itself
+        let issuer = normalize_issuer(&provider.issuer);
+        validation.set_issuer(&[issuer.to_string(), format!("{}/", issuer)]);
+        let claims =
+            decode::<IdTokenClaims>(id_token, &DecodingKey::from_jwk(jwk)?, &validation)?.claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(OidcError("id token nonce does not match".to_string()));
+        }
+        Ok(claims)
+    }
+}
@@ -0,0 +1,29 @@
+use crate::db::Db;
+
+// publish scheduled posts whose time has come, one row per transaction
+pub async fn publish_due_posts(db_pool: &Db) -> Result<u64, sqlx::Error> {
+    let mut published = 0;
+    loop {
+        let mut tx = db_pool.begin().await?;
+        let due = sqlx::query_scalar!(
+            "SELECT id FROM posts WHERE status = 'scheduled' AND publish_at <= NOW() AND deleted_at IS NULL
+             ORDER BY publish_at LIMIT 1 FOR UPDATE SKIP LOCKED"
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(post_id) = due else {
+            return Ok(published);
+        };
+
+        // the post goes live at the time it was scheduled for, not when the job ran
+        sqlx::query!(
+            "UPDATE posts SET status = 'published', published_at = COALESCE(published_at, publish_at),
+             publish_at = NULL WHERE id = ?",
+            post_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        published += 1;
+    }
+}
@@ -0,0 +1,119 @@
+use similar::{ChangeTag, TextDiff};
+use sqlx::MySqlConnection;
+
+use crate::{
+    markdown::render_markdown,
+    models::revision::{DiffChunk, DiffMode, DiffOp},
+    slug::{generate_slug, slugify},
+};
+
+pub async fn record_revision(
+    conn: &mut MySqlConnection,
+    post_id: i64,
+    editor_id: i64,
+    title: &str,
+    body: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO post_revisions (post_id, editor_id, title, body) VALUES (?, ?, ?, ?)",
+        post_id,
+        editor_id,
+        title,
+        body
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+// replaces the post's title and body and records them as a new revision,
+// returns the post's slug and rendered html afterwards
+pub async fn revise_post(
+    conn: &mut MySqlConnection,
+    post_id: i64,
+    editor_id: i64,
+    title: &str,
+    body: &str,
+) -> Result<(String, String), sqlx::Error> {
+    let current = sqlx::query!(
+        "SELECT title, slug FROM posts WHERE id = ? FOR UPDATE",
+        post_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    // posts written before revisions existed get their current state as the first one
+    sqlx::query!(
+        "INSERT INTO post_revisions (post_id, editor_id, title, body, created_at)
+         SELECT id, author_id, title, body, created_at FROM posts
+         WHERE id = ? AND NOT EXISTS (SELECT 1 FROM post_revisions WHERE post_id = ?)",
+        post_id,
+        post_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // a new title gets a new slug, the old one keeps redirecting to it
+    let mut slug = current.slug;
+    if slugify(title) != slugify(&current.title) {
+        let new_slug = generate_slug(&mut *conn, title, Some(post_id)).await?;
+        // a suffixed slug can come out unchanged, "Hello" at hello-2 retitled "Hello 2"
+        if new_slug != slug {
+            // taking back one of this post's own old slugs
+            sqlx::query!(
+                "DELETE FROM post_slug_redirects WHERE old_slug = ?",
+                new_slug
+            )
+            .execute(&mut *conn)
+            .await?;
+            sqlx::query!(
+                "INSERT INTO post_slug_redirects (old_slug, post_id) VALUES (?, ?)
+                 ON DUPLICATE KEY UPDATE post_id = VALUES(post_id)",
+                slug,
+                post_id
+            )
+            .execute(&mut *conn)
+            .await?;
+            slug = new_slug;
+        }
+    }
+
+    // the cached html is rendered again with every edit
+    let body_html = render_markdown(body);
+    sqlx::query!(
+        "UPDATE posts SET title = ?, slug = ?, body = ?, body_html = ? WHERE id = ?",
+        title,
+        slug,
+        body,
+        body_html,
+        post_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    record_revision(&mut *conn, post_id, editor_id, title, body).await?;
+    Ok((slug, body_html))
+}
+
+// consecutive changes of the same kind are merged into one chunk
+pub fn diff_text(old: &str, new: &str, mode: DiffMode) -> Vec<DiffChunk> {
+    let diff = match mode {
+        DiffMode::Line => TextDiff::from_lines(old, new),
+        DiffMode::Word => TextDiff::from_words(old, new),
+    };
+    let mut chunks: Vec<DiffChunk> = Vec::new();
+    for change in diff.iter_all_changes() {
+        let op = match change.tag() {
+            ChangeTag::Equal => DiffOp::Equal,
+            ChangeTag::Insert => DiffOp::Insert,
+            ChangeTag::Delete => DiffOp::Delete,
+        };
+        match chunks.last_mut() {
+            Some(chunk) if chunk.op == op => chunk.text.push_str(change.value()),
+            _ => chunks.push(DiffChunk {
+                op,
+                text: change.value().to_string(),
+            }),
+        }
+    }
+    chunks
+}
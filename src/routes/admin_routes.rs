@@ -0,0 +1,19 @@
+use rocket::Route;
+
+use crate::handlers::admin_handlers::{
+    change_role, delete_user, get_user, get_users, lift_suspension, sign_out_user, suspend_user,
+    unlock_user,
+};
+
+pub fn admin_routes() -> Vec<Route> {
+    routes![
+        get_users,
+        get_user,
+        change_role,
+        suspend_user,
+        lift_suspension,
+        sign_out_user,
+        delete_user,
+        unlock_user
+    ]
+}
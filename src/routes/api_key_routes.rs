@@ -0,0 +1,7 @@
+use rocket::Route;
+
+use crate::handlers::api_key_handlers::{create_api_key, get_api_keys, revoke_api_key};
+
+pub fn api_key_routes() -> Vec<Route> {
+    routes![create_api_key, get_api_keys, revoke_api_key]
+}
@@ -1,7 +1,34 @@
 use rocket::Route;
 
-use crate::handlers::auth_handlers::{sign_in, sign_up};
+use crate::handlers::{
+    auth_handlers::{
+        forgot_password, refresh, resend_verification, reset_password, sign_in, sign_out, sign_up,
+        verify_email,
+    },
+    oidc_handlers::{oidc_callback, oidc_link, oidc_login},
+    two_factor_handlers::{
+        confirm_two_factor, disable_two_factor, enroll_two_factor, regenerate_recovery_codes,
+        verify_two_factor,
+    },
+};
 
 pub fn get_auth_routes() -> Vec<Route> {
-    routes![sign_in, sign_up]
+    routes![
+        sign_in,
+        sign_up,
+        refresh,
+        sign_out,
+        forgot_password,
+        reset_password,
+        verify_email,
+        resend_verification,
+        enroll_two_factor,
+        confirm_two_factor,
+        disable_two_factor,
+        regenerate_recovery_codes,
+        verify_two_factor,
+        oidc_login,
+        oidc_link,
+        oidc_callback
+    ]
 }
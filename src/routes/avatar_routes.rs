@@ -0,0 +1,7 @@
+use rocket::Route;
+
+use crate::handlers::avatar_handlers::get_avatar;
+
+pub fn avatar_routes() -> Vec<Route> {
+    routes![get_avatar]
+}
@@ -0,0 +1,7 @@
+use rocket::Catcher;
+
+use crate::handlers::catchers::{forbidden, unauthorized};
+
+pub fn get_catchers() -> Vec<Catcher> {
+    catchers![unauthorized, forbidden]
+}
@@ -1,9 +1,15 @@
 use rocket::Route;
 
 use crate::handlers::comments_handler::{
-    create_comment, delete_comment, get_comment, update_comment,
+    create_comment, delete_comment, get_comment, restore_comment, update_comment,
 };
 
 pub fn comment_routes() -> Vec<Route> {
-    routes![create_comment, delete_comment, get_comment, update_comment]
+    routes![
+        create_comment,
+        delete_comment,
+        get_comment,
+        update_comment,
+        restore_comment
+    ]
 }
@@ -0,0 +1,7 @@
+use rocket::Route;
+
+use crate::handlers::feed_handlers::get_feed;
+
+pub fn feed_routes() -> Vec<Route> {
+    routes![get_feed]
+}
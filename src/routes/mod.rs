@@ -1,3 +1,15 @@
+pub mod admin_routes;
+pub mod api_key_routes;
 pub mod auth_routes;
+pub mod avatar_routes;
+pub mod catchers;
 pub mod comment_routes;
+pub mod feed_routes;
 pub mod posts_routes;
+pub mod revision_routes;
+pub mod search_routes;
+pub mod taxonomy_routes;
+pub mod trash_routes;
+pub mod user_routes;
+pub mod vanity_routes;
+pub mod well_known_routes;
@@ -1,7 +1,25 @@
 use rocket::Route;
 
-use crate::handlers::post_handlers::{create_post, delete_post, get_post, get_posts, update_post};
+use crate::handlers::post_handlers::{
+    archive_post, create_post, delete_post, get_post, get_post_by_slug, get_posts,
+    get_scheduled_posts, publish_post, restore_post, schedule_post, unpublish_post,
+    unschedule_post, update_post,
+};
 
 pub fn posts_routes() -> Vec<Route> {
-    routes![create_post, delete_post, get_post, update_post, get_posts]
+    routes![
+        create_post,
+        delete_post,
+        get_post,
+        update_post,
+        get_posts,
+        publish_post,
+        unpublish_post,
+        archive_post,
+        schedule_post,
+        unschedule_post,
+        get_scheduled_posts,
+        get_post_by_slug,
+        restore_post
+    ]
 }
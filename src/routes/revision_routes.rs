@@ -0,0 +1,14 @@
+use rocket::Route;
+
+use crate::handlers::revision_handlers::{
+    diff_revisions, get_revision, get_revisions, restore_revision,
+};
+
+pub fn revision_routes() -> Vec<Route> {
+    routes![
+        get_revisions,
+        get_revision,
+        diff_revisions,
+        restore_revision
+    ]
+}
@@ -0,0 +1,7 @@
+use rocket::Route;
+
+use crate::handlers::search_handlers::search;
+
+pub fn search_routes() -> Vec<Route> {
+    routes![search]
+}
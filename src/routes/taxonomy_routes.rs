@@ -0,0 +1,16 @@
+use rocket::Route;
+
+use crate::handlers::taxonomy_handlers::{
+    create_category, delete_category, get_categories, get_tags, merge_tag, rename_tag,
+};
+
+pub fn taxonomy_routes() -> Vec<Route> {
+    routes![
+        get_tags,
+        rename_tag,
+        merge_tag,
+        get_categories,
+        create_category,
+        delete_category
+    ]
+}
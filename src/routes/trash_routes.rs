@@ -0,0 +1,7 @@
+use rocket::Route;
+
+use crate::handlers::trash_handlers::{get_trashed_comments, get_trashed_posts};
+
+pub fn trash_routes() -> Vec<Route> {
+    routes![get_trashed_posts, get_trashed_comments]
+}
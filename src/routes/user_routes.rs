@@ -0,0 +1,37 @@
+use rocket::Route;
+
+use crate::handlers::account_handlers::{
+    cancel_account_deletion, export_account, get_account_deletion, schedule_account_deletion,
+};
+use crate::handlers::avatar_handlers::{delete_avatar, upload_avatar};
+use crate::handlers::feed_handlers::{follow_user, unfollow_user};
+use crate::handlers::post_handlers::get_my_posts;
+use crate::handlers::relation_handlers::{
+    block_user, get_blocks, get_mutes, mute_user, unblock_user, unmute_user,
+};
+use crate::handlers::user::{change_handle, change_password, get_me, get_user, update_me};
+
+pub fn user_routes() -> Vec<Route> {
+    routes![
+        get_me,
+        update_me,
+        change_handle,
+        change_password,
+        upload_avatar,
+        delete_avatar,
+        export_account,
+        get_account_deletion,
+        schedule_account_deletion,
+        cancel_account_deletion,
+        follow_user,
+        unfollow_user,
+        block_user,
+        unblock_user,
+        get_blocks,
+        mute_user,
+        unmute_user,
+        get_mutes,
+        get_my_posts,
+        get_user
+    ]
+}
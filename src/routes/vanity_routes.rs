@@ -0,0 +1,15 @@
+use rocket::Route;
+
+use crate::handlers::vanity_handlers::{
+    get_comments_by_handle, get_post_by_handle, get_posts_by_handle, get_profile_by_handle,
+};
+
+// `/@handle/...` addresses for profiles, posts and comments
+pub fn vanity_routes() -> Vec<Route> {
+    routes![
+        get_profile_by_handle,
+        get_posts_by_handle,
+        get_post_by_handle,
+        get_comments_by_handle
+    ]
+}
@@ -0,0 +1,7 @@
+use rocket::Route;
+
+use crate::handlers::well_known_handlers::jwks;
+
+pub fn well_known_routes() -> Vec<Route> {
+    routes![jwks]
+}
@@ -0,0 +1,212 @@
+pub const MAX_QUERY_LENGTH: usize = 200;
+// InnoDB does not index shorter words (innodb_ft_min_token_size), requiring them would match nothing
+pub const MIN_TERM_LENGTH: usize = 3;
+const SNIPPET_LENGTH: usize = 160;
+
+pub struct SearchQuery {
+    // for MATCH ... AGAINST (? IN BOOLEAN MODE)
+    pub boolean: String,
+    // lowercased words and phrases to highlight
+    pub terms: Vec<String>,
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_TERM_LENGTH)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// every word is required, "quoted text" is matched as a phrase and a trailing * matches prefixes
+pub fn parse_query(q: &str) -> Option<SearchQuery> {
+    let mut boolean = Vec::new();
+    let mut terms = Vec::new();
+    for (i, part) in q.split('"').enumerate() {
+        // odd parts sit between quotes
+        if i % 2 == 1 {
+            let phrase = words(part).join(" ");
+            if !phrase.is_empty() {
+                boolean.push(format!("+\"{}\"", phrase));
+                terms.push(phrase);
+            }
+            continue;
+        }
+        for token in part.split_whitespace() {
+            let prefix = token.ends_with('*');
+            let token_words = words(token);
+            let last = token_words.len().saturating_sub(1);
+            for (j, word) in token_words.into_iter().enumerate() {
+                let wildcard = if prefix && j == last { "*" } else { "" };
+                boolean.push(format!("+{}{}", word, wildcard));
+                terms.push(word);
+            }
+        }
+    }
+    if boolean.is_empty() {
+        return None;
+    }
+    Some(SearchQuery {
+        boolean: boolean.join(" "),
+        terms,
+    })
+}
+
+// length in bytes of `term` at the start of `text`, ignoring case
+fn match_len(text: &str, term: &str) -> Option<usize> {
+    let mut text_chars = text.char_indices();
+    let mut end = 0;
+    for term_char in term.chars() {
+        let (i, c) = text_chars.next()?;
+        if !c.to_lowercase().eq(term_char.to_lowercase()) {
+            return None;
+        }
+        end = i + c.len_utf8();
+    }
+    Some(end)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// an html-escaped excerpt around the first match, with matches wrapped in <mark>
+pub fn snippet(text: &str, terms: &[String]) -> String {
+    let starts_word = |i: usize| {
+        i == 0
+            || !text[..i]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric)
+    };
+    let mut matches = Vec::new();
+    let mut indices = text.char_indices().peekable();
+    while let Some((i, _)) = indices.next() {
+        if !starts_word(i) {
+            continue;
+        }
+        if let Some(len) = terms
+            .iter()
+            .filter_map(|term| match_len(&text[i..], term))
+            .max()
+        {
+            matches.push((i, i + len));
+            // skip to the end of the match
+            while indices.peek().is_some_and(|(j, _)| *j < i + len) {
+                indices.next();
+            }
+        }
+    }
+
+    // a window of about SNIPPET_LENGTH characters starting a little before the first match
+    let first = matches.first().map_or(0, |(start, _)| *start);
+    let start = text[..first]
+        .char_indices()
+        .rev()
+        .take(SNIPPET_LENGTH / 4)
+        .last()
+        .map_or(first, |(i, _)| i);
+    let end = text[start..]
+        .char_indices()
+        .nth(SNIPPET_LENGTH)
+        .map_or(text.len(), |(i, _)| start + i);
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    let mut cursor = start;
+    for (match_start, match_end) in matches {
+        if match_start < start || match_end > end {
+            continue;
+        }
+        out.push_str(&escape_html(&text[cursor..match_start]));
+        out.push_str("<mark>");
+        out.push_str(&escape_html(&text[match_start..match_end]));
+        out.push_str("</mark>");
+        cursor = match_end;
+    }
+    out.push_str(&escape_html(&text[cursor..end]));
+    if end < text.len() {
+        out.push('…');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_query, snippet};
+
+    #[test]
+    fn requires_every_word() {
+        let query = parse_query("Rust  async").unwrap();
+        assert_eq!(query.boolean, "+rust +async");
+        assert_eq!(query.terms, ["rust", "async"]);
+    }
+
+    #[test]
+    fn matches_quoted_phrases() {
+        let query = parse_query("\"Borrow Checker\" rust").unwrap();
+        assert_eq!(query.boolean, "+\"borrow checker\" +rust");
+        assert_eq!(query.terms, ["borrow checker", "rust"]);
+    }
+
+    #[test]
+    fn an_unclosed_quote_runs_to_the_end() {
+        let query = parse_query("rust \"borrow checker").unwrap();
+        assert_eq!(query.boolean, "+rust +\"borrow checker\"");
+    }
+
+    #[test]
+    fn a_trailing_star_matches_prefixes() {
+        assert_eq!(parse_query("prog*").unwrap().boolean, "+prog*");
+        // only the last word of a split token becomes a prefix
+        assert_eq!(parse_query("async-prog*").unwrap().boolean, "+async +prog*");
+    }
+
+    #[test]
+    fn drops_short_words_and_operators() {
+        let query = parse_query("a +rust -go (web)").unwrap();
+        assert_eq!(query.boolean, "+rust +web");
+        assert!(parse_query("a is \"of\"").is_none());
+        assert!(parse_query("   ").is_none());
+    }
+
+    #[test]
+    fn marks_whole_words_ignoring_case() {
+        let terms = ["rust".to_string()];
+        assert_eq!(
+            snippet("Trust in Rust", &terms),
+            "Trust in <mark>Rust</mark>"
+        );
+    }
+
+    #[test]
+    fn marks_multibyte_text() {
+        let terms = ["münchen".to_string(), "grüße".to_string()];
+        assert_eq!(
+            snippet("Grüße aus MÜNCHEN", &terms),
+            "<mark>Grüße</mark> aus <mark>MÜNCHEN</mark>"
+        );
+    }
+
+    #[test]
+    fn cuts_a_window_on_char_boundaries() {
+        let text = format!("{} rust {}", "é".repeat(300), "ü".repeat(300));
+        let out = snippet(&text, &["rust".to_string()]);
+        assert!(out.starts_with('…'));
+        assert!(out.ends_with('…'));
+        assert!(out.contains("<mark>rust</mark>"));
+        assert!(out.chars().count() < 200);
+    }
+
+    #[test]
+    fn escapes_html() {
+        let terms = ["rust".to_string()];
+        assert_eq!(
+            snippet("<b>rust</b> & co", &terms),
+            "&lt;b&gt;<mark>rust</mark>&lt;/b&gt; &amp; co"
+        );
+    }
+}
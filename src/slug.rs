@@ -0,0 +1,155 @@
+use sqlx::MySqlConnection;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::{auth::token::generate_token, db::Db};
+
+pub const MAX_SLUG_LENGTH: usize = 80;
+
+// letters that do not decompose into a base letter plus accents
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'ø' => "o",
+        'đ' | 'ð' => "d",
+        'ł' => "l",
+        'þ' => "th",
+        'ı' => "i",
+        '&' => "and",
+        _ => return None,
+    })
+}
+
+// lowercase ascii words joined by '-', accents are stripped rather than dropped
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title
+        .to_lowercase()
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+    {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if let Some(ascii) = transliterate(c) {
+            slug.push_str(ascii);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug
+        .trim_matches('-')
+        .chars()
+        .take(MAX_SLUG_LENGTH)
+        .collect();
+    let slug = slug.trim_end_matches('-');
+    // titles in scripts we cannot transliterate
+    if slug.is_empty() {
+        "post".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+// a slug is taken by another post's current slug or by one of its old ones
+pub async fn is_slug_available(
+    conn: &mut MySqlConnection,
+    slug: &str,
+    post_id: Option<i64>,
+) -> Result<bool, sqlx::Error> {
+    let post_id = post_id.unwrap_or(0);
+    let taken = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM posts WHERE slug = ? AND id <> ?)
+         OR EXISTS(SELECT 1 FROM post_slug_redirects WHERE old_slug = ? AND post_id <> ?)",
+        slug,
+        post_id,
+        slug,
+        post_id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(taken == 0)
+}
+
+// slug for `title`, with a numeric suffix when another post already has it
+pub async fn generate_slug(
+    conn: &mut MySqlConnection,
+    title: &str,
+    post_id: Option<i64>,
+) -> Result<String, sqlx::Error> {
+    let base = slugify(title);
+    let mut candidates = vec![base.clone()];
+    candidates.extend((2..10).map(|n| format!("{}-{}", base, n)));
+    for candidate in candidates {
+        if is_slug_available(&mut *conn, &candidate, post_id).await? {
+            return Ok(candidate);
+        }
+    }
+    // crowded title, fall back to a random suffix
+    Ok(format!("{}-{}", base, &generate_token()[..6]))
+}
+
+pub enum SlugLookup {
+    Current(i64),
+    // the title was edited, holds the post and its current slug
+    Moved(i64, String),
+}
+
+pub async fn resolve_slug(db_pool: &Db, slug: &str) -> Result<Option<SlugLookup>, sqlx::Error> {
+    let slug = slug.to_lowercase();
+    if let Some(post_id) = sqlx::query_scalar!(
+        "SELECT id FROM posts WHERE slug = ? AND deleted_at IS NULL",
+        slug
+    )
+    .fetch_optional(db_pool)
+    .await?
+    {
+        return Ok(Some(SlugLookup::Current(post_id as i64)));
+    }
+    let moved = sqlx::query!(
+        "SELECT p.id, p.slug FROM post_slug_redirects r JOIN posts p ON p.id = r.post_id
+         WHERE r.old_slug = ? AND p.deleted_at IS NULL",
+        slug
+    )
+    .fetch_optional(db_pool)
+    .await?;
+    Ok(moved.map(|row| SlugLookup::Moved(row.id as i64, row.slug)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slugify, MAX_SLUG_LENGTH};
+
+    #[test]
+    fn joins_words_with_dashes() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  --Rust 2024--  "), "rust-2024");
+    }
+
+    #[test]
+    fn strips_accents() {
+        assert_eq!(slugify("Crème Brûlée"), "creme-brulee");
+        assert_eq!(slugify("Ｆｕｌｌｗｉｄｔｈ"), "fullwidth");
+    }
+
+    #[test]
+    fn transliterates_letters_without_accents() {
+        assert_eq!(slugify("Straße & Øl"), "strasse-and-ol");
+        assert_eq!(slugify("Łódź"), "lodz");
+    }
+
+    #[test]
+    fn falls_back_for_untransliterated_scripts() {
+        assert_eq!(slugify("日本語"), "post");
+        assert_eq!(slugify("!!!"), "post");
+    }
+
+    #[test]
+    fn truncates_without_a_trailing_dash() {
+        assert_eq!(slugify(&"a".repeat(100)).len(), MAX_SLUG_LENGTH);
+        let slug = slugify(&"word ".repeat(30));
+        assert!(slug.len() <= MAX_SLUG_LENGTH);
+        assert!(!slug.ends_with('-'));
+        assert!(slug.starts_with("word-word"));
+    }
+}
@@ -0,0 +1,66 @@
+use std::{
+    env,
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+use super::{Storage, StorageError};
+
+// stores objects as files below STORAGE_DIR
+pub struct LocalStorage {
+    dir: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn from_env() -> Self {
+        let dir = env::var("STORAGE_DIR").unwrap_or_else(|_| "uploads".to_string());
+        LocalStorage { dir: dir.into() }
+    }
+
+    // refuse anything that could escape the storage directory
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        let key = Path::new(key);
+        if key.as_os_str().is_empty()
+            || !key.components().all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(StorageError(format!("invalid key `{}`", key.display())));
+        }
+        Ok(self.dir.join(key))
+    }
+}
+
+#[rocket::async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| StorageError(e.to_string()))?;
+        }
+        tokio::fs::write(path, bytes)
+            .await
+            .map_err(|e| StorageError(e.to_string()))
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(StorageError(e.to_string())),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        let result = match tokio::fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_dir() => tokio::fs::remove_dir_all(&path).await,
+            Ok(_) => tokio::fs::remove_file(&path).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(StorageError(e.to_string())),
+            _ => Ok(()),
+        }
+    }
+}
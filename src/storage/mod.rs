@@ -0,0 +1,37 @@
+use std::{env, fmt, sync::Arc};
+
+pub mod local;
+
+use local::LocalStorage;
+
+#[derive(Debug)]
+pub struct StorageError(pub String);
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "storage error: {}", self.0)
+    }
+}
+
+// keys are relative slash separated paths such as `avatars/12/ab34/64.webp`
+#[rocket::async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), StorageError>;
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError>;
+    // removes the key and everything below it
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+// shared with background jobs, hence an Arc rather than a Box
+pub type Store = Arc<dyn Storage>;
+
+// STORAGE_BACKEND picks the implementation, "local" keeps files on disk
+pub fn storage_from_env() -> Store {
+    match env::var("STORAGE_BACKEND")
+        .unwrap_or_else(|_| "local".to_string())
+        .as_str()
+    {
+        "local" => Arc::new(LocalStorage::from_env()),
+        other => panic!("unknown STORAGE_BACKEND `{}`", other),
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use sqlx::{MySql, MySqlConnection, QueryBuilder};
+
+use crate::{
+    db::Db,
+    models::{post::Post, taxonomy::Category},
+};
+
+pub const MAX_TAG_LENGTH: usize = 50;
+pub const MAX_TAGS_PER_POST: usize = 10;
+
+// tags are compared lowercased, `Rust` and `rust` are the same tag
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+pub fn validate_tag(tag: &str) -> Result<(), String> {
+    if tag.is_empty() || tag.len() > MAX_TAG_LENGTH {
+        return Err(format!(
+            "Tags must be between 1 and {} characters",
+            MAX_TAG_LENGTH
+        ));
+    }
+    if !tag
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_+#.".contains(c))
+    {
+        return Err(format!(
+            "Tag `{}` may only contain letters, digits and '-', '_', '+', '#', '.'",
+            tag
+        ));
+    }
+    Ok(())
+}
+
+// normalized, deduplicated tags for a post
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = normalize_tag(tag);
+        validate_tag(&tag)?;
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > MAX_TAGS_PER_POST {
+        return Err(format!(
+            "A post can have at most {} tags",
+            MAX_TAGS_PER_POST
+        ));
+    }
+    Ok(normalized)
+}
+
+// replaces the post's tags, creating the ones that do not exist yet
+pub async fn set_post_tags(
+    conn: &mut MySqlConnection,
+    post_id: i64,
+    tags: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM post_tags WHERE post_id = ?", post_id)
+        .execute(&mut *conn)
+        .await?;
+    for tag in tags {
+        sqlx::query!("INSERT IGNORE INTO tags (name) VALUES (?)", tag)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query!(
+            "INSERT IGNORE INTO post_tags (post_id, tag_id) SELECT ?, id FROM tags WHERE name = ?",
+            post_id,
+            tag
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+// fills in `tags` for a page of posts with a single query
+pub async fn attach_tags(db_pool: &Db, posts: &mut [Post]) -> Result<(), sqlx::Error> {
+    if posts.is_empty() {
+        return Ok(());
+    }
+    // a bound IN list keeps the post_tags primary key usable
+    let mut query = QueryBuilder::<MySql>::new(
+        "SELECT pt.post_id, t.name FROM post_tags pt JOIN tags t ON t.id = pt.tag_id
+         WHERE pt.post_id IN (",
+    );
+    let mut ids = query.separated(", ");
+    for post in posts.iter() {
+        ids.push_bind(post.id);
+    }
+    query.push(") ORDER BY t.name");
+    let rows: Vec<(i32, String)> = query.build_query_as().fetch_all(db_pool).await?;
+
+    let mut tags: HashMap<i32, Vec<String>> = HashMap::new();
+    for (post_id, name) in rows {
+        tags.entry(post_id).or_default().push(name);
+    }
+    for post in posts.iter_mut() {
+        post.tags = tags.remove(&post.id).unwrap_or_default();
+    }
+    Ok(())
+}
+
+pub async fn category_exists(db_pool: &Db, category_id: i64) -> Result<bool, sqlx::Error> {
+    let exists = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM categories WHERE id = ?)",
+        category_id
+    )
+    .fetch_one(db_pool)
+    .await?;
+    Ok(exists != 0)
+}
+
+// nests a flat list of categories under their parents
+pub fn build_category_tree(categories: Vec<Category>) -> Vec<Category> {
+    let mut children: HashMap<Option<i32>, Vec<Category>> = HashMap::new();
+    for category in categories {
+        children
+            .entry(category.parent_id)
+            .or_default()
+            .push(category);
+    }
+
+    fn attach(category: &mut Category, children: &mut HashMap<Option<i32>, Vec<Category>>) {
+        category.children = children.remove(&Some(category.id)).unwrap_or_default();
+        for child in category.children.iter_mut() {
+            attach(child, children);
+        }
+    }
+
+    let mut roots = children.remove(&None).unwrap_or_default();
+    for root in roots.iter_mut() {
+        attach(root, &mut children);
+    }
+    roots
+}
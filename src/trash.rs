@@ -0,0 +1,19 @@
+use crate::db::Db;
+
+// permanently removes posts and comments that have been in the trash longer than
+// `retention_days`, a purged post takes its comments, tags and revisions with it
+pub async fn purge_trash(db_pool: &Db, retention_days: i64) -> Result<u64, sqlx::Error> {
+    let comments = sqlx::query!(
+        "DELETE FROM comments WHERE deleted_at < NOW() - INTERVAL ? DAY",
+        retention_days
+    )
+    .execute(db_pool)
+    .await?;
+    let posts = sqlx::query!(
+        "DELETE FROM posts WHERE deleted_at < NOW() - INTERVAL ? DAY",
+        retention_days
+    )
+    .execute(db_pool)
+    .await?;
+    Ok(comments.rows_affected() + posts.rows_affected())
+}
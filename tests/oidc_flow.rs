@@ -0,0 +1,215 @@
+// drives the OIDC client through discovery, the PKCE authorisation redirect, the code
+// exchange and JWKS validation against a mock provider
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use blog_api::oidc::{generate_pkce, Oidc, OidcProvider};
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use reqwest::Url;
+use rsa::{pkcs8::DecodePrivateKey, traits::PublicKeyParts, RsaPrivateKey};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const KEY_PEM: &str = include_str!("fixtures/oidc_rsa_key.pem");
+const KEY_ID: &str = "test-key";
+const CLIENT_ID: &str = "blog-api";
+const REDIRECT_URI: &str = "http://localhost:8000/auth/oidc/mock/callback";
+
+fn provider(issuer: &str) -> OidcProvider {
+    OidcProvider {
+        issuer: issuer.to_string(),
+        client_id: CLIENT_ID.to_string(),
+        client_secret: Some("secret".to_string()),
+    }
+}
+
+fn oidc(issuer: &str) -> Oidc {
+    Oidc::new(HashMap::from([("mock".to_string(), provider(issuer))]))
+}
+
+fn jwks() -> Value {
+    let key = RsaPrivateKey::from_pkcs8_pem(KEY_PEM).unwrap();
+    json!({
+        "keys": [{
+            "kty": "RSA",
+            "use": "sig",
+            "alg": "RS256",
+            "kid": KEY_ID,
+            "n": URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+            "e": URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+        }]
+    })
+}
+
+fn id_token(issuer: &str, nonce: &str) -> String {
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(KEY_ID.to_string());
+    let now = Utc::now().timestamp();
+    let claims = json!({
+        "iss": issuer,
+        "aud": CLIENT_ID,
+        "sub": "user-42",
+        "email": "ada@example.com",
+        "email_verified": true,
+        "name": "Ada",
+        "nonce": nonce,
+        "iat": now,
+        "exp": now + 300,
+    });
+    encode(
+        &header,
+        &claims,
+        &EncodingKey::from_rsa_pem(KEY_PEM.as_bytes()).unwrap(),
+    )
+    .unwrap()
+}
+
+// a provider whose discovery document names `issuer`
+async fn mock_provider(issuer: Option<&str>) -> MockServer {
+    let server = MockServer::start().await;
+    let uri = server.uri();
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "issuer": issuer.unwrap_or(&uri),
+            "authorization_endpoint": format!("{}/authorize", uri),
+            "token_endpoint": format!("{}/token", uri),
+            "jwks_uri": format!("{}/jwks", uri),
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(jwks()))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn completes_the_code_flow() {
+    let server = mock_provider(None).await;
+    let issuer = server.uri();
+    let oidc = oidc(&issuer);
+    let provider = &oidc.providers["mock"];
+
+    let metadata = oidc.discover(provider).await.unwrap();
+
+    let pkce = generate_pkce();
+    assert_eq!(
+        pkce.challenge,
+        URL_SAFE_NO_PAD.encode(Sha256::digest(pkce.verifier.as_bytes()))
+    );
+    let url = oidc
+        .authorization_url(
+            &metadata,
+            provider,
+            REDIRECT_URI,
+            "state-1",
+            "nonce-1",
+            &pkce.challenge,
+        )
+        .unwrap();
+    let url = Url::parse(&url).unwrap();
+    assert_eq!(url.path(), "/authorize");
+    let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    assert_eq!(query["response_type"], "code");
+    assert_eq!(query["client_id"], CLIENT_ID);
+    assert_eq!(query["redirect_uri"], REDIRECT_URI);
+    assert_eq!(query["state"], "state-1");
+    assert_eq!(query["nonce"], "nonce-1");
+    assert_eq!(query["code_challenge"], pkce.challenge);
+    assert_eq!(query["code_challenge_method"], "S256");
+
+    // the provider only hands out the token for the code with the matching verifier
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains("grant_type=authorization_code"))
+        .and(body_string_contains("code=auth-code"))
+        .and(body_string_contains(format!(
+            "code_verifier={}",
+            pkce.verifier
+        )))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": "access",
+            "token_type": "Bearer",
+            "id_token": id_token(&issuer, "nonce-1"),
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let token = oidc
+        .exchange_code(
+            &metadata,
+            provider,
+            "auth-code",
+            REDIRECT_URI,
+            &pkce.verifier,
+        )
+        .await
+        .unwrap();
+    let claims = oidc
+        .validate_id_token(&metadata, provider, &token, "nonce-1")
+        .await
+        .unwrap();
+    assert_eq!(claims.sub, "user-42");
+    assert_eq!(claims.email.as_deref(), Some("ada@example.com"));
+    assert_eq!(claims.email_verified, Some(true));
+}
+
+#[tokio::test]
+async fn accepts_a_configured_issuer_with_trailing_slash() {
+    let server = mock_provider(None).await;
+    let oidc = oidc(&format!("{}/", server.uri()));
+    let provider = &oidc.providers["mock"];
+
+    let metadata = oidc.discover(provider).await.unwrap();
+    let token = id_token(&server.uri(), "nonce-1");
+    assert!(oidc
+        .validate_id_token(&metadata, provider, &token, "nonce-1")
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn rejects_a_discovery_document_for_another_issuer() {
+    let server = mock_provider(Some("https://evil.example.com")).await;
+    let oidc = oidc(&server.uri());
+    let provider = &oidc.providers["mock"];
+
+    assert!(oidc.discover(provider).await.is_err());
+}
+
+#[tokio::test]
+async fn rejects_tokens_from_another_issuer() {
+    let server = mock_provider(None).await;
+    let oidc = oidc(&server.uri());
+    let provider = &oidc.providers["mock"];
+
+    let metadata = oidc.discover(provider).await.unwrap();
+    let token = id_token("https://evil.example.com", "nonce-1");
+    assert!(oidc
+        .validate_id_token(&metadata, provider, &token, "nonce-1")
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn rejects_a_mismatched_nonce() {
+    let server = mock_provider(None).await;
+    let oidc = oidc(&server.uri());
+    let provider = &oidc.providers["mock"];
+
+    let metadata = oidc.discover(provider).await.unwrap();
+    let token = id_token(&server.uri(), "nonce-1");
+    assert!(oidc
+        .validate_id_token(&metadata, provider, &token, "another-nonce")
+        .await
+        .is_err());
+}